@@ -21,6 +21,30 @@ pub enum Commands {
         #[arg(required = false, value_name = "QUERY")]
         /// The query sequence to optimize
         query: Option<String>,
+
+        #[arg(short, long, value_name = "ORGANISM")]
+        /// The organism to optimize for
+        organism: i32,
+    },
+
+    /// Print CAI, RCA, %MinMax and GC content in sliding windows along a DNA sequence,
+    /// as tab-separated values
+    Profile {
+        #[arg(required = false, value_name = "QUERY")]
+        /// The DNA sequence to profile
+        query: Option<String>,
+
+        #[arg(short, long, value_name = "ORGANISM")]
+        /// The organism whose codon usage the sequence is scored against
+        organism: i32,
+
+        #[arg(short, long, default_value_t = 18)]
+        /// Window size, in codons
+        window: usize,
+
+        #[arg(short, long, default_value_t = 1)]
+        /// Step between windows, in codons
+        step: usize,
     },
 
     /// Pull usage data from the database
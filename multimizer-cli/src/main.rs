@@ -1,30 +1,48 @@
 pub mod cli;
 pub mod codon_usage;
+pub mod optimize;
+pub mod profile;
 pub mod utils;
 
 use clap::Parser;
+use multimizer::profile::ProfileOptions;
 
 use cli::Cli;
 use std::io;
 
 use crate::codon_usage::pull_codon_usage_for_org;
+use crate::optimize::optimize_for_org;
+use crate::profile::profile_for_org;
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.cmd {
-        Some(cli::Commands::Optimize { query }) => {
-            let query = match query {
-                Some(q) => q,
-                None => {
-                    let mut input = String::new();
-                    io::stdin()
-                        .read_line(&mut input)
-                        .expect("Failed to read from stdin");
-                    input.trim().to_string()
-                }
+        Some(cli::Commands::Optimize { query, organism }) => {
+            let query = query.unwrap_or_else(read_query);
+            let result = optimize_for_org(&query, organism).expect("Failed to optimize query");
+            println!("{}", result.seq);
+        }
+        Some(cli::Commands::Profile {
+            query,
+            organism,
+            window,
+            step,
+        }) => {
+            let query = query.unwrap_or_else(read_query);
+            let options = ProfileOptions {
+                window_size: window,
+                step,
             };
-            println!("Optimizing {query}");
+            let profile =
+                profile_for_org(&query, organism, &options).expect("Failed to profile query");
+            println!("start\tend\tcai\trca\tmin_max\tgc");
+            for w in profile.windows {
+                println!(
+                    "{}\t{}\t{:.4}\t{:.4}\t{:.2}\t{:.4}",
+                    w.start, w.end, w.cai, w.rca, w.min_max, w.gc
+                );
+            }
         }
         Some(cli::Commands::Pull { id }) => {
             let codon_usage = pull_codon_usage_for_org(id).expect("Failed to pull codon usage");
@@ -33,3 +51,11 @@ fn main() {
         None => unreachable!(),
     }
 }
+
+fn read_query() -> String {
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read from stdin");
+    input.trim().to_string()
+}
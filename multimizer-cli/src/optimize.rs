@@ -0,0 +1,27 @@
+use multimizer::db::Database;
+use multimizer::optimizations::{
+    optimize_for_single_organism, OptimizationOptions, OptimizationResult,
+};
+use multimizer::utils::compute_rca_xyz_table;
+
+use anyhow::Result;
+
+use crate::utils::get_database_file_path;
+
+///
+/// Optimize a query for an organism in the database, with the constraints that suit it as a
+/// host (see `OptimizationOptions::with_host_defaults`)
+///
+pub fn optimize_for_org(query: &str, org_id: i32) -> Result<OptimizationResult> {
+    let db_path = get_database_file_path()?;
+    let db = Database::new(db_path)?;
+    let organism = db.get_organism(org_id)?;
+    let codon_usage = db.get_codon_usage_for_organism(&org_id)?;
+
+    // the rca is scored against the raw counts, which the converted usage table loses
+    let options = OptimizationOptions {
+        rca_reference: Some(compute_rca_xyz_table(&codon_usage)),
+        ..OptimizationOptions::default().with_host_defaults(&organism)
+    };
+    optimize_for_single_organism(query, &codon_usage.into(), &options)
+}
@@ -0,0 +1,22 @@
+use multimizer::db::Database;
+use multimizer::profile::{compute_adaptation_profile, AdaptationProfile, ProfileOptions};
+
+use anyhow::Result;
+
+use crate::utils::get_database_file_path;
+
+///
+/// Compute the adaptation profile of a DNA sequence against the codon usage of an organism
+/// in the database (see `multimizer::profile::compute_adaptation_profile`)
+///
+pub fn profile_for_org(
+    dna: &str,
+    org_id: i32,
+    options: &ProfileOptions,
+) -> Result<AdaptationProfile> {
+    let db_path = get_database_file_path()?;
+    let db = Database::new(db_path)?;
+    let codon_usage = db.get_codon_usage_for_organism(&org_id)?;
+
+    compute_adaptation_profile(dna, &codon_usage.into(), options)
+}
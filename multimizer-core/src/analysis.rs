@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::consts::{AACodonLibrary, CodonToAA};
+use crate::models::{Codon, CodonUsage};
+use crate::utils::split_into_codons;
+
+///
+/// Codon counts of a gene (or a whole genome) and the classic codon bias statistics that
+/// can be computed from them: RSCU, the effective number of codons, Fop and CBI.
+///
+/// Codons are assigned to residues with the translation table, and the synonymous codon
+/// families come from `AACodonLibrary`. Start and stop codons are counted like any other
+/// codon, but single-codon residues and stops are left out of every statistic that is
+/// about synonymous codon choice.
+///
+#[derive(Debug, Clone)]
+pub struct CodonCounts {
+    codon_counts: [u64; 64],
+}
+
+impl CodonCounts {
+    ///
+    /// Count the codons of an in-frame coding sequence
+    ///
+    pub fn from_sequence(dna: &str) -> Result<CodonCounts> {
+        let mut codon_counts = [0u64; 64];
+        for codon in split_into_codons(&dna.to_uppercase())? {
+            codon_counts[codon.index()] += 1;
+        }
+        Ok(CodonCounts { codon_counts })
+    }
+
+    ///
+    /// Take the codon counts of a codon usage table
+    ///
+    pub fn from_codon_usage(codon_usage: &CodonUsage) -> CodonCounts {
+        let mut codon_counts = [0u64; 64];
+        for (codon, count) in &codon_usage.codon_usage {
+            codon_counts[codon.index()] = (*count).max(0) as u64;
+        }
+        CodonCounts { codon_counts }
+    }
+
+    ///
+    /// Number of times a codon was observed
+    ///
+    pub fn count(&self, codon: &Codon) -> u64 {
+        self.codon_counts[codon.index()]
+    }
+
+    /// Synonymous codon families with more than one codon, keyed by residue
+    fn degenerate_families(&self) -> Vec<(char, Vec<Codon>)> {
+        let codon_to_aa = CodonToAA::new();
+        let mut families: Vec<(char, Vec<Codon>)> = AACodonLibrary::new()
+            .into_iter()
+            .filter(|(aa, codons)| *aa != '*' && codons.len() > 1)
+            .map(|(aa, codons)| {
+                let codons = codons
+                    .into_iter()
+                    .filter(|codon| codon_to_aa.convert(codon) == Some(aa))
+                    .collect();
+                (aa, codons)
+            })
+            .collect();
+        families.sort_by_key(|(aa, _)| *aa);
+        families
+    }
+
+    ///
+    /// Compute the relative synonymous codon usage (RSCU) of every codon -- how often it is
+    /// used relative to uniform use of its synonymous codons. A value of 1 means no bias.
+    ///
+    /// # Returns
+    /// - the rscu of every codon of the residues that were observed
+    ///
+    pub fn rscu(&self) -> HashMap<Codon, f64> {
+        let mut rscu = HashMap::new();
+        for (_, codons) in AACodonLibrary::new() {
+            let total: u64 = codons.iter().map(|codon| self.count(codon)).sum();
+            if total == 0 {
+                continue;
+            }
+            let expected = total as f64 / codons.len() as f64;
+            for codon in codons {
+                rscu.insert(codon, self.count(&codon) as f64 / expected);
+            }
+        }
+        rscu
+    }
+
+    ///
+    /// Compute the effective number of codons (ENC, Nc) following Wright (1990). It ranges
+    /// from 20 (one codon per residue) to 61 (uniform use of all sense codons).
+    ///
+    /// The homozygosity F of every residue observed at least twice is averaged within its
+    /// degeneracy class (2, 3, 4 or 6 codons). A missing isoleucine (the only 3-fold class)
+    /// is estimated as the mean of the 2- and 4-fold classes, as suggested by Wright. Any
+    /// other class without data leaves the ENC undefined.
+    ///
+    /// # Returns
+    /// - the enc, or None when a degeneracy class has no data
+    ///
+    pub fn enc(&self) -> Option<f64> {
+        // degeneracy -> (number of families, homozygosity of the observed ones)
+        let mut classes: HashMap<usize, (usize, Vec<f64>)> = HashMap::new();
+        for (_, codons) in self.degenerate_families() {
+            let counts: Vec<f64> = codons.iter().map(|c| self.count(c) as f64).collect();
+            let n: f64 = counts.iter().sum();
+
+            let class = classes.entry(codons.len()).or_insert((0, vec![]));
+            class.0 += 1;
+            if n < 2.0 {
+                continue;
+            }
+            let sum_squares: f64 = counts.iter().map(|x| (x / n).powi(2)).sum();
+            class.1.push((n * sum_squares - 1.0) / (n - 1.0));
+        }
+
+        let mean_f = |degeneracy: usize| -> Option<f64> {
+            let (_, f) = classes.get(&degeneracy)?;
+            (!f.is_empty()).then(|| f.iter().sum::<f64>() / f.len() as f64)
+        };
+
+        let mut enc = 0.0;
+        let mut degeneracies: Vec<usize> = classes.keys().copied().collect();
+        degeneracies.sort();
+        for degeneracy in degeneracies {
+            let families = classes[&degeneracy].0 as f64;
+            let f = match mean_f(degeneracy) {
+                Some(f) => f,
+                None if degeneracy == 3 => (mean_f(2)? + mean_f(4)?) / 2.0,
+                None => return None,
+            };
+            // a class can't contribute more than all of its codons
+            enc += (families / f).min(families * degeneracy as f64);
+        }
+
+        // single-codon residues (M and W) contribute one each
+        let single_codon_residues = AACodonLibrary::new()
+            .into_iter()
+            .filter(|(aa, codons)| *aa != '*' && codons.len() == 1)
+            .count();
+        Some(enc + single_codon_residues as f64)
+    }
+
+    ///
+    /// The most used codon of every degenerate residue, e.g. to use as the optimal codons of
+    /// a host in `fop` and `cbi`. Residues that were never observed are left out.
+    ///
+    pub fn preferred_codons(&self) -> Vec<Codon> {
+        self.degenerate_families()
+            .into_iter()
+            .filter_map(|(_, codons)| {
+                codons
+                    .into_iter()
+                    .filter(|codon| self.count(codon) > 0)
+                    .max_by_key(|codon| self.count(codon))
+            })
+            .collect()
+    }
+
+    /// Observed and total counts of the residues that have at least one optimal codon, along
+    /// with the number of optimal codons expected from uniform codon usage
+    fn optimal_codon_counts(&self, optimal: &[Codon]) -> (f64, f64, f64) {
+        let (mut observed, mut total, mut expected) = (0.0, 0.0, 0.0);
+        for (_, codons) in self.degenerate_families() {
+            let num_optimal = codons.iter().filter(|c| optimal.contains(c)).count();
+            if num_optimal == 0 {
+                continue;
+            }
+            let n: f64 = codons.iter().map(|c| self.count(c) as f64).sum();
+            observed += codons
+                .iter()
+                .filter(|c| optimal.contains(c))
+                .map(|c| self.count(c) as f64)
+                .sum::<f64>();
+            total += n;
+            expected += n * num_optimal as f64 / codons.len() as f64;
+        }
+        (observed, total, expected)
+    }
+
+    ///
+    /// Compute the frequency of optimal codons (Fop, Ikemura 1981) -- the fraction of the
+    /// codons of residues with an optimal codon that are optimal.
+    ///
+    /// # Arguments
+    /// - the optimal codons, see `preferred_codons`
+    ///
+    /// # Returns
+    /// - the fop, between 0 and 1
+    ///
+    pub fn fop(&self, optimal: &[Codon]) -> f64 {
+        let (observed, total, _) = self.optimal_codon_counts(optimal);
+        if total == 0.0 {
+            return 0.0;
+        }
+        observed / total
+    }
+
+    ///
+    /// Compute the codon bias index (CBI, Bennetzen and Hall 1982) -- like Fop, but corrected
+    /// for the optimal codons expected from uniform codon usage. It is 0 for uniform usage and
+    /// 1 when only optimal codons are used.
+    ///
+    /// # Arguments
+    /// - the optimal codons, see `preferred_codons`
+    ///
+    /// # Returns
+    /// - the cbi, at most 1
+    ///
+    pub fn cbi(&self, optimal: &[Codon]) -> f64 {
+        let (observed, total, expected) = self.optimal_codon_counts(optimal);
+        if total - expected <= 0.0 {
+            return 0.0;
+        }
+        (observed - expected) / (total - expected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const EPSILON: f64 = 1e-9;
+
+    /// Every degenerate residue twice, optionally without isoleucine
+    fn single_codon_gene(with_isoleucine: bool) -> String {
+        let mut residues: Vec<(char, Vec<Codon>)> = AACodonLibrary::new().into_iter().collect();
+        residues.sort_by_key(|(aa, _)| *aa);
+        residues
+            .into_iter()
+            .filter(|(aa, codons)| *aa != '*' && codons.len() > 1)
+            .filter(|(aa, _)| with_isoleucine || *aa != 'I')
+            .map(|(_, codons)| codons[0].to_string().repeat(2))
+            .collect()
+    }
+
+    #[rstest]
+    fn test_enc() {
+        let enc = CodonCounts::from_sequence(&single_codon_gene(true))
+            .unwrap()
+            .enc()
+            .unwrap();
+        assert_eq!((enc - 20.0).abs() < EPSILON, true);
+
+        // isoleucine is estimated from the 2- and 4-fold classes
+        let enc = CodonCounts::from_sequence(&single_codon_gene(false))
+            .unwrap()
+            .enc()
+            .unwrap();
+        assert_eq!((enc - 20.0).abs() < EPSILON, true);
+
+        // every sense codon once
+        let uniform: String = Codon::ALL
+            .iter()
+            .filter(|codon| CodonToAA::new().convert(codon) != Some('*'))
+            .map(|codon| codon.to_string())
+            .collect();
+        let enc = CodonCounts::from_sequence(&uniform).unwrap().enc().unwrap();
+        assert_eq!((enc - 61.0).abs() < EPSILON, true);
+
+        // no 6-fold residues at all
+        assert_eq!(CodonCounts::from_sequence("GCTAAA").unwrap().enc(), None);
+    }
+
+    #[rstest]
+    fn test_rscu_fop_cbi() {
+        let counts = CodonCounts::from_sequence("GCTGCTGCCAAA").unwrap();
+
+        let rscu = counts.rscu();
+        assert_eq!((rscu[&Codon::GCT] - 8.0 / 3.0).abs() < EPSILON, true);
+        assert_eq!((rscu[&Codon::GCA]).abs() < EPSILON, true);
+        assert_eq!((rscu[&Codon::AAA] - 2.0).abs() < EPSILON, true);
+        assert_eq!(rscu.get(&Codon::TTA), None);
+
+        let optimal = counts.preferred_codons();
+        assert_eq!(optimal, vec![Codon::GCT, Codon::AAA]);
+        assert_eq!((counts.fop(&optimal) - 0.75).abs() < EPSILON, true);
+        // 3 optimal codons against 1.25 expected, out of 4
+        assert_eq!((counts.cbi(&optimal) - 1.75 / 2.75).abs() < EPSILON, true);
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use rand::Rng;
+
+use crate::consts::SequenceType;
+use crate::models::Codon;
+use crate::optimizations::{
+    apply_locked_codons, finalize_optimization, resolve_locked_codons, CodonUsageByResidue,
+    OptimizationOptions, OptimizationResult,
+};
+use crate::sampling::CodonSampler;
+use crate::synthesis::{synthesis_complexity, SynthesisComplexityOptions};
+use crate::utils::{
+    compute_codon_usage_error, compute_gc_content, detect_sequence_type, find_iupac_matches,
+    reverse_complement, translate_dna_sequence,
+};
+
+///
+/// Weighted objective for the simulated annealing optimizer. Every term is a
+/// penalty, so lower scores are better.
+///
+#[derive(Debug, Clone)]
+pub struct AnnealingObjective {
+    /// Weight of the codon usage error (see `compute_codon_usage_error`)
+    pub usage_weight: f64,
+    /// Weight of the distance between the GC content and `gc_target`
+    pub gc_weight: f64,
+    /// Target GC content, as a fraction between 0 and 1
+    pub gc_target: f64,
+    /// Weight of each occurrence of a forbidden motif
+    pub motif_weight: f64,
+    /// Motifs (IUPAC patterns) that should not appear on either strand of the sequence
+    pub forbidden_motifs: Vec<String>,
+    /// Weight of each repeated k-mer
+    pub repeat_weight: f64,
+    /// Length of the k-mers considered for repeats
+    pub repeat_length: usize,
+    /// Weight of the synthesis complexity score (see `synthesis::synthesis_complexity`)
+    pub synthesis_weight: f64,
+    /// Thresholds for the synthesis complexity checks
+    pub synthesis_options: SynthesisComplexityOptions,
+}
+
+impl Default for AnnealingObjective {
+    fn default() -> Self {
+        AnnealingObjective {
+            usage_weight: 1.0,
+            gc_weight: 1.0,
+            gc_target: 0.5,
+            motif_weight: 1.0,
+            forbidden_motifs: vec![],
+            repeat_weight: 0.1,
+            repeat_length: 12,
+            synthesis_weight: 0.0,
+            synthesis_options: SynthesisComplexityOptions::default(),
+        }
+    }
+}
+
+impl AnnealingObjective {
+    ///
+    /// Score a DNA sequence against the objective
+    ///
+    /// # Arguments
+    /// - dna sequence
+    /// - codon usage data for organism
+    ///
+    /// # Returns
+    /// - the score, lower is better
+    ///
+    pub fn score(&self, dna: &str, codon_usage: &CodonUsageByResidue) -> Result<f64> {
+        let mut score = 0.0;
+
+        if self.usage_weight != 0.0 {
+            score += self.usage_weight * compute_codon_usage_error(dna, codon_usage)?;
+        }
+        if self.gc_weight != 0.0 {
+            score += self.gc_weight * (compute_gc_content(dna) - self.gc_target).abs();
+        }
+        if self.motif_weight != 0.0 {
+            let occurrences: usize = self
+                .forbidden_motifs
+                .iter()
+                .map(|motif| count_occurrences(dna, motif))
+                .sum();
+            score += self.motif_weight * occurrences as f64;
+        }
+        if self.repeat_weight != 0.0 {
+            score += self.repeat_weight * count_repeated_kmers(dna, self.repeat_length) as f64;
+        }
+        if self.synthesis_weight != 0.0 {
+            score +=
+                self.synthesis_weight * synthesis_complexity(dna, &self.synthesis_options).score;
+        }
+
+        Ok(score)
+    }
+}
+
+///
+/// Cooling schedule for simulated annealing. The temperature decays geometrically
+/// from `initial_temperature` to `final_temperature` over `max_iterations`.
+///
+#[derive(Debug, Clone)]
+pub struct AnnealingSchedule {
+    pub initial_temperature: f64,
+    pub final_temperature: f64,
+}
+
+impl Default for AnnealingSchedule {
+    fn default() -> Self {
+        AnnealingSchedule {
+            initial_temperature: 0.1,
+            final_temperature: 1e-4,
+        }
+    }
+}
+
+///
+/// Snapshot of the optimizer after a single iteration
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnealingProgress {
+    pub iteration: i32,
+    pub temperature: f64,
+    /// Score of the current sequence
+    pub score: f64,
+    /// Best score seen so far
+    pub best_score: f64,
+}
+
+pub struct AnnealingResult {
+    pub result: OptimizationResult,
+    /// Best score of the search, i.e. `best_score` of the last progress step. The sequence
+    /// constraints are enforced afterwards, so the returned sequence can score differently.
+    pub best_score: f64,
+    pub progress: Vec<AnnealingProgress>,
+}
+
+///
+/// Optimize a query sequence with simulated annealing over synonymous codon substitutions.
+/// This sequence can be either protein or DNA.
+///
+/// The initial sequence is generated with `options.strategy`. Every iteration mutates a
+/// single codon to a synonymous one (sampled from the usage data) and accepts the mutation
+/// according to the Metropolis criterion. The best sequence seen is returned, together with
+/// the score progress of every iteration. The sequence constraints of `options` are
+/// enforced on the best sequence afterwards.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for organism
+/// - the objective to minimize
+/// - the cooling schedule
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence and the score progress
+///
+pub fn optimize_with_annealing(
+    query: &str,
+    codon_usage: &CodonUsageByResidue,
+    objective: &AnnealingObjective,
+    schedule: &AnnealingSchedule,
+    options: &OptimizationOptions,
+) -> Result<AnnealingResult> {
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+    let protein = match detect_sequence_type(query)? {
+        SequenceType::Dna => translate_dna_sequence(query)?,
+        SequenceType::Protein => query.to_string(),
+    };
+
+    let constraints = options.build_constraints()?;
+    let mut sampler = CodonSampler::new(codon_usage, options.seed as u64)?;
+    let mut current = options
+        .strategy
+        .generate(&protein, codon_usage, &mut sampler)?;
+    apply_locked_codons(&mut current, &locked);
+    let mut current_score = objective.score(&codons_to_string(&current), codon_usage)?;
+
+    let mut best = current.clone();
+    let mut best_score = current_score;
+
+    // only unlocked positions with synonymous alternatives can be mutated
+    let mutable_positions: Vec<usize> = protein
+        .chars()
+        .enumerate()
+        .filter(|(i, _)| locked.get(*i).is_none_or(Option::is_none))
+        .filter(|(_, residue)| codon_usage.get(residue).is_some_and(|p| p.len() > 1))
+        .map(|(i, _)| i)
+        .collect();
+
+    let max_iterations = options.max_iterations.max(1);
+    let cooling_rate = if max_iterations > 1 {
+        (schedule.final_temperature / schedule.initial_temperature)
+            .powf(1.0 / (max_iterations - 1) as f64)
+    } else {
+        1.0
+    };
+
+    let mut temperature = schedule.initial_temperature;
+    let mut progress = Vec::with_capacity(max_iterations as usize);
+    let residues: Vec<char> = protein.chars().collect();
+
+    for iteration in 0..max_iterations {
+        if !mutable_positions.is_empty() {
+            let position = mutable_positions[sampler.rng().gen_range(0..mutable_positions.len())];
+            let proposal = sampler.sample(residues[position])?;
+
+            if proposal != current[position] {
+                let previous = current[position];
+                current[position] = proposal;
+
+                let proposal_score = objective.score(&codons_to_string(&current), codon_usage)?;
+                let delta = proposal_score - current_score;
+                let accept = delta <= 0.0
+                    || (temperature > 0.0
+                        && sampler.rng().gen::<f64>() < (-delta / temperature).exp());
+
+                if accept {
+                    current_score = proposal_score;
+                    if current_score < best_score {
+                        best_score = current_score;
+                        best = current.clone();
+                    }
+                } else {
+                    current[position] = previous;
+                }
+            }
+        }
+
+        progress.push(AnnealingProgress {
+            iteration: iteration + 1,
+            temperature,
+            score: current_score,
+            best_score,
+        });
+
+        temperature *= cooling_rate;
+    }
+
+    let result = finalize_optimization(
+        best,
+        codon_usage,
+        &constraints,
+        options,
+        &locked,
+        max_iterations,
+    )?;
+
+    Ok(AnnealingResult {
+        result,
+        best_score,
+        progress,
+    })
+}
+
+fn codons_to_string(codons: &[Codon]) -> String {
+    codons.iter().map(|codon| codon.to_string()).collect()
+}
+
+/// Count (possibly overlapping) occurrences of an IUPAC motif on both strands
+fn count_occurrences(dna: &str, motif: &str) -> usize {
+    let forward = find_iupac_matches(dna, motif).len();
+    let reverse = reverse_complement(motif);
+    if reverse.eq_ignore_ascii_case(motif) {
+        return forward;
+    }
+    forward + find_iupac_matches(dna, &reverse).len()
+}
+
+/// Count the k-mers that already occurred earlier in the sequence
+fn count_repeated_kmers(dna: &str, k: usize) -> usize {
+    if k == 0 || k > dna.len() {
+        return 0;
+    }
+    let mut seen: HashSet<&[u8]> = HashSet::new();
+    dna.as_bytes()
+        .windows(k)
+        .filter(|kmer| !seen.insert(kmer))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.25),
+                    (Codon::GCC, 0.25),
+                    (Codon::GCA, 0.25),
+                    (Codon::GCG, 0.25),
+                ]),
+            ),
+            ('K', HashMap::from([(Codon::AAA, 0.5), (Codon::AAG, 0.5)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_objective_score(usage: CodonUsageByResidue) {
+        let objective = AnnealingObjective {
+            usage_weight: 0.0,
+            forbidden_motifs: vec!["GCCGCC".to_string()],
+            repeat_weight: 0.0,
+            ..Default::default()
+        };
+
+        // 100% GC and two (overlapping) forbidden motifs
+        let score = objective.score("GCCGCCGCC", &usage).unwrap();
+        assert_eq!((score - 2.5).abs() < 1e-9, true);
+
+        // IUPAC motifs match on both strands
+        let objective = AnnealingObjective {
+            usage_weight: 0.0,
+            gc_weight: 0.0,
+            forbidden_motifs: vec!["AARG".to_string()],
+            repeat_weight: 0.0,
+            ..Default::default()
+        };
+        // AAAG on the forward strand, and CCTT -- AAGG on the reverse strand
+        let score = objective.score("AAAGCCTTA", &usage).unwrap();
+        assert_eq!((score - 2.0).abs() < 1e-9, true);
+
+        // synthesis complexity on its own
+        let objective = AnnealingObjective {
+            usage_weight: 0.0,
+            gc_weight: 0.0,
+            repeat_weight: 0.0,
+            synthesis_weight: 2.0,
+            ..Default::default()
+        };
+        let complexity = synthesis_complexity("GCCGCCGCC", &objective.synthesis_options);
+        let score = objective.score("GCCGCCGCC", &usage).unwrap();
+        assert_eq!(complexity.score > 0.0, true);
+        assert_eq!((score - 2.0 * complexity.score).abs() < 1e-9, true);
+    }
+
+    #[rstest]
+    fn test_optimize_with_annealing(usage: CodonUsageByResidue) {
+        let objective = AnnealingObjective {
+            usage_weight: 0.0,
+            gc_target: 0.0,
+            repeat_weight: 0.0,
+            ..Default::default()
+        };
+        let options = OptimizationOptions {
+            max_iterations: 500,
+            ..Default::default()
+        };
+
+        let res = optimize_with_annealing(
+            "AKAKAKAK",
+            &usage,
+            &objective,
+            &AnnealingSchedule::default(),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(res.result.translated_seq, "AKAKAKAK");
+        assert_eq!(res.progress.len(), 500);
+        assert_eq!(res.best_score, res.progress.last().unwrap().best_score);
+
+        // the best score never gets worse
+        for step in res.progress.windows(2) {
+            assert_eq!(step[1].best_score <= step[0].best_score, true);
+        }
+
+        // minimizing GC should end up with the AT rich codons -- GCT/GCA and AAA
+        let gc = compute_gc_content(&res.result.seq);
+        assert_eq!((gc - 1.0 / 3.0).abs() < 1e-9, true);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::consts::{CodonToAA, SequenceType};
+use crate::models::Codon;
+use crate::optimizations::{
+    finalize_optimization, resolve_locked_codons, CodonUsageByResidue, OptimizationOptions,
+    OptimizationResult,
+};
+use crate::utils::{
+    detect_sequence_type, normalize_codon_usage, split_into_codons, translate_dna_sequence,
+};
+
+/// Pseudocount used for codon pairs that never occur in the reference set
+const CODON_PAIR_PSEUDOCOUNT: f64 = 0.5;
+
+///
+/// Codon pair usage model -- a 64x64 table of how often each (in-frame) codon pair
+/// occurs in a set of reference coding sequences.
+///
+#[derive(Debug, Clone)]
+pub struct CodonPairUsage {
+    pair_counts: Vec<[u64; 64]>,
+    codon_counts: [u64; 64],
+    /// residue of every codon, by codon index
+    residues: [char; 64],
+    /// codon and codon pair counts summed up to residues and residue pairs
+    aa_counts: HashMap<char, f64>,
+    aa_pair_counts: HashMap<(char, char), f64>,
+}
+
+impl CodonPairUsage {
+    ///
+    /// Build a codon pair usage table from a set of reference coding sequences
+    ///
+    /// # Arguments
+    /// - sequences: in-frame coding sequences
+    ///
+    /// # Returns
+    /// - the codon pair usage table
+    ///
+    pub fn from_sequences<S: AsRef<str>>(sequences: &[S]) -> Result<CodonPairUsage> {
+        let mut pair_counts = vec![[0u64; 64]; 64];
+        let mut codon_counts = [0u64; 64];
+
+        for seq in sequences {
+            let codons = split_into_codons(seq.as_ref())?;
+            for codon in &codons {
+                codon_counts[codon.index()] += 1;
+            }
+            for pair in codons.windows(2) {
+                pair_counts[pair[0].index()][pair[1].index()] += 1;
+            }
+        }
+
+        let codon_to_aa = CodonToAA::new();
+        let mut residues = ['*'; 64];
+        for codon in Codon::ALL {
+            residues[codon.index()] = codon_to_aa.convert(&codon).unwrap();
+        }
+
+        // sum the codon and codon pair counts up to amino acids and amino acid pairs
+        let mut aa_counts: HashMap<char, f64> = HashMap::new();
+        let mut aa_pair_counts: HashMap<(char, char), f64> = HashMap::new();
+        for a in Codon::ALL {
+            let aa_a = residues[a.index()];
+            *aa_counts.entry(aa_a).or_insert(0.0) += codon_counts[a.index()] as f64;
+            for b in Codon::ALL {
+                let aa_b = residues[b.index()];
+                *aa_pair_counts.entry((aa_a, aa_b)).or_insert(0.0) +=
+                    pair_counts[a.index()][b.index()] as f64;
+            }
+        }
+
+        Ok(CodonPairUsage {
+            pair_counts,
+            codon_counts,
+            residues,
+            aa_counts,
+            aa_pair_counts,
+        })
+    }
+
+    ///
+    /// Number of times a codon pair was observed
+    ///
+    pub fn count(&self, first: &Codon, second: &Codon) -> u64 {
+        self.pair_counts[first.index()][second.index()]
+    }
+
+    ///
+    /// Compute the codon pair score (CPS) of a codon pair.
+    ///
+    /// It works according to the following formula (Coleman et al. 2008):
+    /// $$
+    /// CPS(AB) = \ln \left( \frac{N_{AB}}{\frac{N_A N_B}{N_X N_Y} N_{XY}} \right)
+    /// $$
+    /// where A and B are codons encoding the amino acids X and Y. Positive values are
+    /// over-represented pairs, negative values under-represented ones. Pairs that were
+    /// never observed get a pseudocount, and pairs with no information at all (one of
+    /// the codons or the amino acid pair never occurs) score zero.
+    ///
+    /// # Arguments
+    /// - the two codons, in order
+    ///
+    /// # Returns
+    /// - the codon pair score
+    ///
+    pub fn codon_pair_score(&self, first: &Codon, second: &Codon) -> f64 {
+        let aa_x = self.residues[first.index()];
+        let aa_y = self.residues[second.index()];
+
+        let n_a = self.codon_counts[first.index()] as f64;
+        let n_b = self.codon_counts[second.index()] as f64;
+        let n_x = self.aa_counts.get(&aa_x).copied().unwrap_or(0.0);
+        let n_y = self.aa_counts.get(&aa_y).copied().unwrap_or(0.0);
+        let n_xy = self
+            .aa_pair_counts
+            .get(&(aa_x, aa_y))
+            .copied()
+            .unwrap_or(0.0);
+
+        if n_a == 0.0 || n_b == 0.0 || n_xy == 0.0 {
+            return 0.0;
+        }
+
+        let n_ab = match self.count(first, second) {
+            0 => CODON_PAIR_PSEUDOCOUNT,
+            n => n as f64,
+        };
+        let expected = (n_a * n_b) / (n_x * n_y) * n_xy;
+
+        (n_ab / expected).ln()
+    }
+
+    ///
+    /// Precompute the codon pair score for every one of the 64x64 codon pairs
+    ///
+    pub fn codon_pair_scores(&self) -> Vec<[f64; 64]> {
+        let mut scores = vec![[0.0; 64]; 64];
+        for a in Codon::ALL {
+            for b in Codon::ALL {
+                scores[a.index()][b.index()] = self.codon_pair_score(&a, &b);
+            }
+        }
+        scores
+    }
+}
+
+///
+/// Compute the codon pair bias (CPB) of a DNA sequence -- the mean codon pair
+/// score over all adjacent codon pairs.
+///
+/// # Arguments
+/// - dna sequence
+/// - the codon pair usage table
+///
+/// # Returns
+/// - the codon pair bias
+///
+pub fn compute_codon_pair_bias(dna: &str, pair_usage: &CodonPairUsage) -> Result<f64> {
+    let codons = split_into_codons(dna)?;
+    if codons.len() < 2 {
+        return Ok(0.0);
+    }
+
+    let scores = pair_usage.codon_pair_scores();
+    let total: f64 = codons
+        .windows(2)
+        .map(|pair| scores[pair[0].index()][pair[1].index()])
+        .sum();
+
+    Ok(total / (codons.len() - 1) as f64)
+}
+
+///
+/// Optimize a query sequence taking codon pair context into account. This sequence
+/// can be either protein or DNA.
+///
+/// Adjacent codons are chosen together with a Viterbi search over the codon lattice
+/// of the protein, maximizing
+/// $$
+/// \sum_i \ln p(c_i) + w \sum_i CPS(c_{i-1}, c_i)
+/// $$
+/// where `p` is the per-residue codon preference and `w` is `options.codon_pair_weight`.
+/// A weight of zero reduces to picking the most frequent codon for every residue.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for organism
+/// - codon pair usage data for organism
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence
+///
+pub fn optimize_with_codon_pairs(
+    query: &str,
+    codon_usage: &CodonUsageByResidue,
+    pair_usage: &CodonPairUsage,
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+    let protein = match detect_sequence_type(query)? {
+        SequenceType::Dna => translate_dna_sequence(query)?,
+        SequenceType::Protein => query.to_string(),
+    };
+
+    let normalized_usage = normalize_codon_usage(codon_usage);
+    let pair_scores = pair_usage.codon_pair_scores();
+
+    // candidate codons (and their log preference) for every position
+    let mut lattice: Vec<Vec<(Codon, f64)>> = Vec::with_capacity(protein.len());
+    for (position, residue) in protein.chars().enumerate() {
+        let Some(preferences) = normalized_usage.get(&residue) else {
+            anyhow::bail!("Invalid residue passed in: {residue}")
+        };
+        if let Some(codon) = locked.get(position).copied().flatten() {
+            lattice.push(vec![(codon, 0.0)]);
+            continue;
+        }
+        let mut candidates: Vec<(Codon, f64)> = preferences
+            .iter()
+            .filter(|(_, pref)| **pref > 0.0)
+            .map(|(codon, pref)| (*codon, pref.ln()))
+            .collect();
+        candidates.sort_by_key(|(codon, _)| *codon);
+        if candidates.is_empty() {
+            anyhow::bail!("No codons available for residue {residue}")
+        }
+        lattice.push(candidates);
+    }
+
+    let mut optimized_sequence = String::with_capacity(protein.len() * 3);
+
+    if !lattice.is_empty() {
+        // scores[i][j] -- best score of any path ending in codon j at position i
+        let mut scores: Vec<Vec<f64>> = vec![lattice[0].iter().map(|(_, s)| *s).collect()];
+        let mut backpointers: Vec<Vec<usize>> = vec![vec![0; lattice[0].len()]];
+
+        for i in 1..lattice.len() {
+            let mut position_scores = Vec::with_capacity(lattice[i].len());
+            let mut position_backpointers = Vec::with_capacity(lattice[i].len());
+
+            for (codon, log_pref) in &lattice[i] {
+                let (best_prev, best_score) = lattice[i - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(k, (prev_codon, _))| {
+                        let pair_score = pair_scores[prev_codon.index()][codon.index()];
+                        (k, scores[i - 1][k] + options.codon_pair_weight * pair_score)
+                    })
+                    .max_by(|(_, s1), (_, s2)| s1.total_cmp(s2))
+                    .unwrap();
+
+                position_scores.push(best_score + log_pref);
+                position_backpointers.push(best_prev);
+            }
+
+            scores.push(position_scores);
+            backpointers.push(position_backpointers);
+        }
+
+        // trace back the best path
+        let last = scores.len() - 1;
+        let mut best = scores[last]
+            .iter()
+            .enumerate()
+            .max_by(|(_, s1), (_, s2)| s1.total_cmp(s2))
+            .map(|(j, _)| j)
+            .unwrap();
+
+        let mut path = vec![lattice[last][best].0];
+        for i in (1..lattice.len()).rev() {
+            best = backpointers[i][best];
+            path.push(lattice[i - 1][best].0);
+        }
+        path.reverse();
+
+        for codon in path {
+            optimized_sequence.push_str(&codon.to_string());
+        }
+    }
+
+    let constraints = options.build_constraints()?;
+    let codons = split_into_codons(&optimized_sequence)?;
+
+    finalize_optimization(codons, codon_usage, &constraints, options, &locked, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            ('A', HashMap::from([(Codon::GCT, 0.3), (Codon::GCC, 0.7)])),
+            ('K', HashMap::from([(Codon::AAA, 0.6), (Codon::AAG, 0.4)])),
+        ])
+    }
+
+    #[fixture]
+    fn pair_usage() -> CodonPairUsage {
+        // GCC is always followed by AAG, GCT by AAA
+        CodonPairUsage::from_sequences(&["GCCAAGGCCAAG", "GCTAAAGCTAAA", "GCCAAGGCTAAA"]).unwrap()
+    }
+
+    #[rstest]
+    fn test_codon_pair_score(pair_usage: CodonPairUsage) {
+        assert_eq!(pair_usage.count(&Codon::GCC, &Codon::AAG), 3);
+        assert_eq!(
+            pair_usage.codon_pair_score(&Codon::GCC, &Codon::AAG) > 0.0,
+            true
+        );
+        assert_eq!(
+            pair_usage.codon_pair_score(&Codon::GCC, &Codon::AAA) < 0.0,
+            true
+        );
+
+        let cpb = compute_codon_pair_bias("GCCAAGGCTAAA", &pair_usage).unwrap();
+        assert_eq!(cpb > 0.0, true);
+    }
+
+    #[rstest]
+    fn test_optimize_with_codon_pairs(usage: CodonUsageByResidue, pair_usage: CodonPairUsage) {
+        // no pair weight -- just the most frequent codons
+        let options = OptimizationOptions {
+            codon_pair_weight: 0.0,
+            ..Default::default()
+        };
+        let result = optimize_with_codon_pairs("AK", &usage, &pair_usage, &options).unwrap();
+        assert_eq!(result.seq, "GCCAAA");
+
+        // strong pair weight -- GCC/AAA is a poor pair, so we pick the best scoring good pair
+        let options = OptimizationOptions {
+            codon_pair_weight: 10.0,
+            ..Default::default()
+        };
+        let result = optimize_with_codon_pairs("AK", &usage, &pair_usage, &options).unwrap();
+        assert_eq!(result.seq, "GCCAAG");
+    }
+}
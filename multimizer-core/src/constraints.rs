@@ -0,0 +1,799 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::consts::CodonToAA;
+use crate::models::{Codon, Organism};
+use crate::optimizations::CodonUsageByResidue;
+
+///
+/// A region of a DNA sequence that breaks a constraint
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Name of the constraint that was broken
+    pub constraint: String,
+    /// Start of the offending region (0-based nucleotide position, inclusive)
+    pub start: usize,
+    /// End of the offending region (0-based nucleotide position, exclusive)
+    pub end: usize,
+    /// How badly the constraint is broken. Fixing a violation should lower its penalty,
+    /// so the repair step can make progress on violations that take more than one change.
+    pub penalty: f64,
+    pub description: String,
+}
+
+///
+/// A synonymous codon substitution made to remove a constraint violation
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodonChange {
+    /// Position of the codon (0-based nucleotide position of its first base)
+    pub position: usize,
+    pub original: Codon,
+    pub replacement: Codon,
+    /// Name of the constraint whose violation prompted the change
+    pub constraint: String,
+}
+
+///
+/// Outcome of enforcing constraints on a sequence
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConstraintReport {
+    /// Violations that could not be removed
+    pub violations: Vec<Violation>,
+    /// Every substitution that was made, in order
+    pub changes: Vec<CodonChange>,
+}
+
+/// Scores a substitution: takes the position of the change and the new nucleotides, and
+/// returns the change in penalty (see `SequenceConstraint::change_scorer`)
+pub type ChangeScorer<'a> = Box<dyn Fn(usize, &str) -> f64 + 'a>;
+
+///
+/// A constraint on the nucleotide sequence of an optimized gene -- e.g. no restriction sites,
+/// GC content within bounds, no long homopolymers.
+///
+/// Constraints are enforced after optimization by synonymous codon substitution, see
+/// `enforce_constraints`.
+///
+pub trait SequenceConstraint: Send + Sync {
+    /// Name of the constraint, used to label violations
+    fn name(&self) -> &str;
+
+    ///
+    /// Scan a DNA sequence for violations of the constraint
+    ///
+    /// # Arguments
+    /// - dna sequence
+    ///
+    /// # Returns
+    /// - every violation found, empty if the sequence satisfies the constraint
+    ///
+    fn find_violations(&self, dna: &str) -> Vec<Violation>;
+
+    ///
+    /// Whether the constraint is hard (e.g. restriction sites and motifs). The repair step
+    /// never makes a change that adds to the violations of hard constraints, whatever it
+    /// gains on soft ones.
+    ///
+    fn is_hard(&self) -> bool {
+        false
+    }
+
+    ///
+    /// Whether the constraint is strict: the repair step never makes a change that adds to
+    /// its penalty, whatever it gains on the other constraints, hard ones included
+    ///
+    fn is_strict(&self) -> bool {
+        false
+    }
+
+    ///
+    /// How many nucleotides around a change the violations it affects can reach, or None if
+    /// a change can affect violations anywhere in the sequence. Used by the default
+    /// `change_scorer` to only rescan the region around a change.
+    ///
+    fn context(&self) -> Option<usize> {
+        None
+    }
+
+    ///
+    /// Build a function that scores substitutions in a DNA sequence for the repair step:
+    /// given the position of a change and the new nucleotides, it returns the change in
+    /// total penalty. The default rescans `context` nucleotides on either side of the
+    /// change, or the whole sequence without a context.
+    ///
+    /// # Arguments
+    /// - dna sequence before any change
+    ///
+    /// # Returns
+    /// - the scoring function
+    ///
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        Box::new(move |start, replacement| {
+            let end = start + replacement.len();
+            let (from, to) = match self.context() {
+                Some(context) => (
+                    start.saturating_sub(context),
+                    end.saturating_add(context).min(dna.len()),
+                ),
+                None => (0, dna.len()),
+            };
+            let changed = format!("{}{replacement}{}", &dna[from..start], &dna[end..to]);
+            let penalty = |seq: &str| -> f64 {
+                self.find_violations(seq)
+                    .iter()
+                    .map(|violation| violation.penalty)
+                    .sum()
+            };
+            penalty(&changed) - penalty(&dna[from..to])
+        })
+    }
+}
+
+///
+/// Wraps a constraint to make it strict (see `SequenceConstraint::is_strict`), e.g. to keep
+/// the repair step from undoing what the optimizer guarantees
+///
+pub struct StrictConstraint<C: SequenceConstraint>(pub C);
+
+impl<C: SequenceConstraint> SequenceConstraint for StrictConstraint<C> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        self.0.find_violations(dna)
+    }
+
+    fn is_hard(&self) -> bool {
+        self.0.is_hard()
+    }
+
+    fn is_strict(&self) -> bool {
+        true
+    }
+
+    fn context(&self) -> Option<usize> {
+        self.0.context()
+    }
+
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        self.0.change_scorer(dna)
+    }
+}
+
+///
+/// Find all violations of a set of constraints
+///
+/// # Arguments
+/// - dna sequence
+/// - the constraints
+///
+/// # Returns
+/// - every violation found
+///
+pub fn find_violations(dna: &str, constraints: &[Box<dyn SequenceConstraint>]) -> Vec<Violation> {
+    constraints
+        .iter()
+        .flat_map(|constraint| constraint.find_violations(dna))
+        .collect()
+}
+
+fn codons_to_string(codons: &[Codon]) -> String {
+    codons.iter().map(|codon| codon.to_string()).collect()
+}
+
+///
+/// Remove constraint violations from a sequence by synonymous codon substitution.
+///
+/// Violations of hard constraints are visited first, then those of soft constraints, in
+/// order of position. For each one, every synonymous alternative of every codon overlapping
+/// the violation is scored locally (see `SequenceConstraint::change_scorer`) and the best
+/// change is applied. Changes are ranked by their effect on the hard constraints first and
+/// on the soft ones second, so a hard violation is never traded for a soft one; ties go to
+/// the change that costs the least codon usage preference. A change is only made if it
+/// lowers the penalty of the hard constraints, or of the soft ones without adding to the
+/// hard ones, and never if it adds to the penalty of a strict constraint, whatever it gains
+/// on the others. Violations that can't be improved are skipped, and whatever is left at the
+/// end is returned. Locked codons are never changed, but violations that span them are still
+/// repaired using the codons around them.
+///
+/// # Arguments
+/// - codons: the sequence to repair, modified in place
+/// - codon_usage: usage data to draw synonymous codons from
+/// - constraints: the constraints to enforce
+/// - locked: positions that hold a codon are locked, may be empty if nothing is locked
+///
+/// # Returns
+/// - the violations that could not be removed and the changes that were made
+///
+pub fn enforce_constraints(
+    codons: &mut [Codon],
+    codon_usage: &CodonUsageByResidue,
+    constraints: &[Box<dyn SequenceConstraint>],
+    locked: &[Option<Codon>],
+) -> ConstraintReport {
+    if constraints.is_empty() {
+        return ConstraintReport::default();
+    }
+
+    let codon_to_aa = CodonToAA::new();
+
+    // preference of a codon for its residue, used to break ties between fixes
+    let preference = |codon: &Codon| -> f64 {
+        codon_to_aa
+            .convert(codon)
+            .and_then(|aa| codon_usage.get(&aa))
+            .and_then(|preferences| preferences.get(codon))
+            .copied()
+            .unwrap_or(0.0)
+    };
+
+    // synonymous codons, most used first
+    let synonyms = |codon: &Codon| -> Vec<Codon> {
+        let Some(preferences) = codon_to_aa
+            .convert(codon)
+            .and_then(|aa| codon_usage.get(&aa))
+        else {
+            return vec![];
+        };
+        let mut alternatives: Vec<(Codon, f64)> = preferences
+            .iter()
+            .filter(|(alternative, _)| *alternative != codon)
+            .map(|(alternative, pref)| (*alternative, *pref))
+            .collect();
+        alternatives.sort_by(|(c1, p1), (c2, p2)| p2.total_cmp(p1).then(c1.cmp(c2)));
+        alternatives.into_iter().map(|(codon, _)| codon).collect()
+    };
+
+    let mut changes: Vec<CodonChange> = vec![];
+    let mut unfixable: HashSet<(String, usize, usize)> = HashSet::new();
+    let max_rounds = 4 * codons.len().max(25);
+
+    for _ in 0..max_rounds {
+        let dna = codons_to_string(codons);
+
+        // hard violations first, each group in order of position
+        let mut violations: Vec<(bool, Violation)> = constraints
+            .iter()
+            .flat_map(|constraint| {
+                let is_hard = constraint.is_hard();
+                constraint
+                    .find_violations(&dna)
+                    .into_iter()
+                    .map(move |violation| (is_hard, violation))
+            })
+            .collect();
+        violations.sort_by_key(|(is_hard, violation)| (!is_hard, violation.start));
+
+        let Some((_, violation)) = violations
+            .iter()
+            .find(|(_, v)| !unfixable.contains(&(v.constraint.clone(), v.start, v.end)))
+        else {
+            break;
+        };
+
+        let scorers: Vec<(bool, bool, ChangeScorer)> = constraints
+            .iter()
+            .map(|constraint| {
+                (
+                    constraint.is_hard(),
+                    constraint.is_strict(),
+                    constraint.change_scorer(&dna),
+                )
+            })
+            .collect();
+
+        let first_codon = violation.start / 3;
+        let last_codon = (violation.end.max(violation.start + 1) - 1) / 3;
+
+        // (position, codon, (hard penalty change, soft penalty change), loss in preference)
+        let mut best: Option<(usize, Codon, (f64, f64), f64)> = None;
+        let overlapping = codons
+            .iter()
+            .copied()
+            .enumerate()
+            .take(last_codon + 1)
+            .skip(first_codon);
+        for (position, original) in overlapping {
+            if locked.get(position).is_some_and(Option::is_some) {
+                continue;
+            }
+            for alternative in synonyms(&original) {
+                let replacement = alternative.to_string();
+                let (mut hard, mut soft) = (0.0, 0.0);
+                let mut adds_strict = false;
+                for (is_hard, is_strict, scorer) in &scorers {
+                    let change = scorer(position * 3, &replacement);
+                    adds_strict |= *is_strict && change > 1e-12;
+                    if *is_hard {
+                        hard += change;
+                    } else {
+                        soft += change;
+                    }
+                }
+                if adds_strict {
+                    continue;
+                }
+                let loss = preference(&original) - preference(&alternative);
+
+                let improves = hard < -1e-12 || (hard <= 1e-12 && soft < -1e-12);
+                let is_better = match best {
+                    None => improves,
+                    Some((_, _, (best_hard, best_soft), best_loss)) => {
+                        hard < best_hard - 1e-12
+                            || (hard <= best_hard + 1e-12
+                                && (soft < best_soft - 1e-12
+                                    || (soft <= best_soft + 1e-12 && loss < best_loss)))
+                    }
+                };
+                if is_better {
+                    best = Some((position, alternative, (hard, soft), loss));
+                }
+            }
+        }
+
+        match best {
+            Some((position, alternative, _, _)) => {
+                changes.push(CodonChange {
+                    position: position * 3,
+                    original: codons[position],
+                    replacement: alternative,
+                    constraint: violation.constraint.clone(),
+                });
+                codons[position] = alternative;
+            }
+            None => {
+                unfixable.insert((violation.constraint.clone(), violation.start, violation.end));
+            }
+        }
+    }
+
+    ConstraintReport {
+        violations: find_violations(&codons_to_string(codons), constraints),
+        changes,
+    }
+}
+
+fn validate_gc_range(min: f64, max: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&min) || !(0.0..=1.0).contains(&max) || min > max {
+        anyhow::bail!("Invalid GC content range: {min} - {max}")
+    }
+    Ok(())
+}
+
+/// How far the GC content of `len` bases holding `gc` G/C bases falls outside the allowed
+/// range, as a fraction between 0 and 1 (0 if inside)
+fn gc_deviation(gc: usize, len: usize, min: f64, max: f64) -> f64 {
+    if len == 0 {
+        return 0.0;
+    }
+    let fraction = gc as f64 / len as f64;
+    if fraction > max {
+        fraction - max
+    } else if fraction < min {
+        min - fraction
+    } else {
+        0.0
+    }
+}
+
+///
+/// Constraint on the GC content of the whole sequence. Bounds are fractions between 0 and 1,
+/// and so is the penalty: how far the GC content falls outside them.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct GcContentConstraint {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl GcContentConstraint {
+    pub fn new(min: f64, max: f64) -> Result<GcContentConstraint> {
+        validate_gc_range(min, max)?;
+        Ok(GcContentConstraint { min, max })
+    }
+
+    ///
+    /// Target the GC content of a host organism
+    ///
+    /// # Arguments
+    /// - the host organism
+    /// - how far the GC content may deviate from the host's, as a fraction (e.g. 0.05)
+    ///
+    /// # Returns
+    /// - the constraint
+    ///
+    pub fn for_organism(organism: &Organism, tolerance: f64) -> Result<GcContentConstraint> {
+        let gc = organism.gc_perc as f64 / 100.0;
+        GcContentConstraint::new(
+            (gc - tolerance).clamp(0.0, 1.0),
+            (gc + tolerance).clamp(0.0, 1.0),
+        )
+    }
+}
+
+impl SequenceConstraint for GcContentConstraint {
+    fn name(&self) -> &str {
+        "gc-content"
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let gc = count_gc(dna.as_bytes());
+        let penalty = gc_deviation(gc, dna.len(), self.min, self.max);
+        if penalty == 0.0 {
+            return vec![];
+        }
+
+        vec![Violation {
+            constraint: self.name().to_string(),
+            start: 0,
+            end: dna.len(),
+            penalty,
+            description: format!(
+                "GC content {:.3} outside {:.3} - {:.3}",
+                gc as f64 / dna.len() as f64,
+                self.min,
+                self.max
+            ),
+        }]
+    }
+
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        // only the G/C count matters, so a change can be scored without a rescan
+        let gc = count_gc(dna.as_bytes());
+        let penalty = gc_deviation(gc, dna.len(), self.min, self.max);
+        Box::new(move |start, replacement| {
+            let replaced = &dna.as_bytes()[start..start + replacement.len()];
+            let changed_gc = gc + count_gc(replacement.as_bytes()) - count_gc(replaced);
+            gc_deviation(changed_gc, dna.len(), self.min, self.max) - penalty
+        })
+    }
+}
+
+///
+/// Constraint on the GC content of every window of `window_size` nucleotides (sliding by one).
+/// Sequences shorter than the window are checked as a single window. Every window that breaks
+/// the constraint is reported on its own, with a penalty of how far its GC content falls
+/// outside the bounds (as a fraction), so a change is credited for every window it improves.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct GcWindowConstraint {
+    pub window_size: usize,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl GcWindowConstraint {
+    pub fn new(window_size: usize, min: f64, max: f64) -> Result<GcWindowConstraint> {
+        if window_size == 0 {
+            anyhow::bail!("GC window size must be positive")
+        }
+        validate_gc_range(min, max)?;
+        Ok(GcWindowConstraint {
+            window_size,
+            min,
+            max,
+        })
+    }
+}
+
+impl SequenceConstraint for GcWindowConstraint {
+    fn name(&self) -> &str {
+        "gc-window"
+    }
+
+    fn context(&self) -> Option<usize> {
+        Some(self.window_size)
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        if dna.is_empty() {
+            return vec![];
+        }
+
+        let bases = dna.as_bytes();
+        let window_size = self.window_size.min(bases.len());
+
+        // gc_prefix[i] is the number of G/C bases before position i
+        let mut gc_prefix = Vec::with_capacity(bases.len() + 1);
+        gc_prefix.push(0);
+        for base in bases {
+            gc_prefix.push(gc_prefix.last().unwrap() + count_gc(&[*base]));
+        }
+
+        (0..=(bases.len() - window_size))
+            .filter_map(|start| {
+                let gc = gc_prefix[start + window_size] - gc_prefix[start];
+                let penalty = gc_deviation(gc, window_size, self.min, self.max);
+                (penalty > 0.0).then(|| Violation {
+                    constraint: self.name().to_string(),
+                    start,
+                    end: start + window_size,
+                    penalty,
+                    description: format!(
+                        "GC content {:.3} of {} bp window outside {:.3} - {:.3}",
+                        gc as f64 / window_size as f64,
+                        window_size,
+                        self.min,
+                        self.max
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+fn count_gc(bases: &[u8]) -> usize {
+    bases
+        .iter()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+        .count()
+}
+
+///
+/// Constraint against low complexity runs: homopolymers (capped per base, in nucleotides) and
+/// dinucleotide/trinucleotide tandem repeats (capped in number of consecutive copies of the unit).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LowComplexityConstraint {
+    pub max_homopolymer: HashMap<char, usize>,
+    pub max_dinucleotide_repeats: usize,
+    pub max_trinucleotide_repeats: usize,
+}
+
+impl LowComplexityConstraint {
+    pub fn new(
+        max_homopolymer: usize,
+        max_dinucleotide_repeats: usize,
+        max_trinucleotide_repeats: usize,
+    ) -> LowComplexityConstraint {
+        LowComplexityConstraint {
+            max_homopolymer: "ACGT".chars().map(|base| (base, max_homopolymer)).collect(),
+            max_dinucleotide_repeats,
+            max_trinucleotide_repeats,
+        }
+    }
+
+    /// Set the longest homopolymer allowed for a single base
+    pub fn with_max_homopolymer(mut self, base: char, max_length: usize) -> Self {
+        self.max_homopolymer
+            .insert(base.to_ascii_uppercase(), max_length);
+        self
+    }
+
+    fn homopolymer_violations(&self, bases: &[u8]) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut start = 0;
+        while start < bases.len() {
+            let base = bases[start];
+            let end = start + bases[start..].iter().take_while(|b| **b == base).count();
+            let max_length = self
+                .max_homopolymer
+                .get(&(base as char))
+                .copied()
+                .unwrap_or(usize::MAX);
+            if end - start > max_length {
+                violations.push(Violation {
+                    constraint: "homopolymer".to_string(),
+                    start,
+                    end,
+                    penalty: (end - start - max_length) as f64,
+                    description: format!(
+                        "{} bp run of {} at position {start} (max {max_length})",
+                        end - start,
+                        base as char
+                    ),
+                });
+            }
+            start = end;
+        }
+        violations
+    }
+
+    fn tandem_repeat_violations(
+        &self,
+        bases: &[u8],
+        period: usize,
+        max_repeats: usize,
+    ) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut start = 0;
+        while start + period < bases.len() {
+            // extend the periodic region [start, end) as far as it goes
+            let mut end = start + period;
+            while end < bases.len() && bases[end] == bases[end - period] {
+                end += 1;
+            }
+
+            let unit = &bases[start..start + period];
+            let copies = (end - start) / period;
+            // homopolymers are periodic too, but they're handled separately
+            let is_homopolymer = unit.iter().all(|b| *b == unit[0]);
+            if copies > max_repeats && !is_homopolymer {
+                violations.push(Violation {
+                    constraint: "tandem-repeat".to_string(),
+                    start,
+                    end,
+                    penalty: (end - start - max_repeats * period) as f64,
+                    description: format!(
+                        "{copies} copies of {} at position {start} (max {max_repeats})",
+                        String::from_utf8_lossy(unit)
+                    ),
+                });
+            }
+
+            start = if end - start > period {
+                end - period + 1
+            } else {
+                start + 1
+            };
+        }
+        violations
+    }
+}
+
+impl Default for LowComplexityConstraint {
+    fn default() -> Self {
+        LowComplexityConstraint::new(8, 6, 5)
+            .with_max_homopolymer('C', 5)
+            .with_max_homopolymer('G', 5)
+    }
+}
+
+impl SequenceConstraint for LowComplexityConstraint {
+    fn name(&self) -> &str {
+        "low-complexity"
+    }
+
+    fn context(&self) -> Option<usize> {
+        let longest = self
+            .max_homopolymer
+            .values()
+            .copied()
+            .chain([
+                self.max_dinucleotide_repeats.saturating_mul(2),
+                self.max_trinucleotide_repeats.saturating_mul(3),
+            ])
+            .max()
+            .unwrap_or(0);
+        // one more unit than allowed is enough to see a violation
+        Some(longest.saturating_add(3))
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let bases = dna.as_bytes();
+        let mut violations = self.homopolymer_violations(bases);
+        violations.extend(self.tandem_repeat_violations(bases, 2, self.max_dinucleotide_repeats));
+        violations.extend(self.tandem_repeat_violations(bases, 3, self.max_trinucleotide_repeats));
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    use crate::restriction::RestrictionSiteConstraint;
+    use crate::utils::{compute_gc_content, split_into_codons};
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.4),
+                    (Codon::GCC, 0.3),
+                    (Codon::GCA, 0.2),
+                    (Codon::GCG, 0.1),
+                ]),
+            ),
+            ('K', HashMap::from([(Codon::AAA, 0.7), (Codon::AAG, 0.3)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_gc_content_constraint(usage: CodonUsageByResidue) {
+        let constraints: Vec<Box<dyn SequenceConstraint>> =
+            vec![Box::new(GcContentConstraint::new(0.3, 0.5).unwrap())];
+
+        // 2 of 12 bases are G/C
+        let mut codons = split_into_codons("AAAAAAAAAGCT").unwrap();
+        assert_eq!(find_violations("AAAAAAAAAGCT", &constraints).len(), 1);
+
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+        let dna = codons_to_string(&codons);
+        assert_eq!(remaining, vec![]);
+        assert_eq!(compute_gc_content(&dna) >= 0.3, true);
+
+        assert_eq!(GcContentConstraint::new(0.6, 0.5).is_err(), true);
+    }
+
+    #[rstest]
+    fn test_gc_window_constraint(usage: CodonUsageByResidue) {
+        let constraints: Vec<Box<dyn SequenceConstraint>> =
+            vec![Box::new(GcWindowConstraint::new(6, 0.3, 1.0).unwrap())];
+
+        // the six windows starting in 5..=10 each fall below 30% G/C, four with none at all
+        let dna = "GCCGCCAAAAAAAAAGCCGCC";
+        let violations = find_violations(dna, &constraints);
+        let spans: Vec<(usize, usize)> = violations.iter().map(|v| (v.start, v.end)).collect();
+        assert_eq!(
+            spans,
+            (5..=10).map(|start| (start, start + 6)).collect::<Vec<_>>()
+        );
+        let penalty: f64 = violations.iter().map(|v| v.penalty).sum();
+        assert_eq!(
+            (penalty - (4.0 * 0.3 + 2.0 * (0.3 - 1.0 / 6.0))).abs() < 1e-9,
+            true
+        );
+
+        let mut codons = split_into_codons(dna).unwrap();
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+        let dna = codons_to_string(&codons);
+        assert_eq!(remaining, vec![]);
+        assert_eq!(dna, "GCCGCCAAGAAGAAGGCCGCC");
+    }
+
+    #[rstest]
+    fn test_hard_constraints_come_first() {
+        // the only way to raise the GC content creates a SmaI site (CCCGGG)
+        let usage: CodonUsageByResidue = HashMap::from([
+            ('P', HashMap::from([(Codon::CCA, 0.6), (Codon::CCC, 0.4)])),
+            ('G', HashMap::from([(Codon::GGG, 1.0)])),
+        ]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![
+            Box::new(GcWindowConstraint::new(3, 1.0, 1.0).unwrap()),
+            Box::new(RestrictionSiteConstraint::from_names(&["SmaI"]).unwrap()),
+        ];
+
+        let mut codons = split_into_codons("CCAGGG").unwrap();
+        let report = enforce_constraints(&mut codons, &usage, &constraints, &[]);
+        assert_eq!(codons_to_string(&codons), "CCAGGG");
+        assert_eq!(report.changes, vec![]);
+        assert_eq!(
+            report
+                .violations
+                .iter()
+                .all(|v| v.constraint == "gc-window"),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_low_complexity_constraint(usage: CodonUsageByResidue) {
+        let constraint = LowComplexityConstraint::new(5, 3, 3).with_max_homopolymer('G', 3);
+
+        let violations = constraint.find_violations("CAAAAAAGGGGTCACACACAT");
+        let found: Vec<(&str, usize, usize)> = violations
+            .iter()
+            .map(|v| (v.constraint.as_str(), v.start, v.end))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("homopolymer", 1, 7),
+                ("homopolymer", 7, 11),
+                ("tandem-repeat", 12, 20),
+            ]
+        );
+
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![Box::new(constraint)];
+        let mut codons = split_into_codons("AAAAAAAAAAAAGCAGCAGCAGCA").unwrap();
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+        assert_eq!(remaining, vec![]);
+        assert_eq!(
+            find_violations(&codons_to_string(&codons), &constraints),
+            vec![]
+        );
+    }
+}
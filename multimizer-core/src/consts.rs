@@ -6,6 +6,7 @@ pub const DEFAULT_PROHIBITED_CODON_THRESHOLD: f64 = 0.1;
 pub const DEFAULT_ITERATIONS: i32 = 1000;
 pub const VALID_AMINO_ACIDS: &str = "ACDEFGHIKLMNPQRSTVWY*";
 pub const VALID_NUCLEOTIDES: &str = "ACGT";
+pub const VALID_IUPAC_NUCLEOTIDES: &str = "ACGTRYSWKMBDHVN";
 
 pub struct NumCodonsByAA {
     pub num_codons: HashMap<char, i32>,
@@ -193,11 +194,11 @@ impl CodonToAA {
             (Codon::TTG, 'L'),
             (Codon::TAC, 'Y'),
             (Codon::TAT, 'Y'),
-            (Codon::TAA, '_'),
-            (Codon::TAG, '_'),
+            (Codon::TAA, '*'),
+            (Codon::TAG, '*'),
             (Codon::TGC, 'C'),
             (Codon::TGT, 'C'),
-            (Codon::TGA, '_'),
+            (Codon::TGA, '*'),
             (Codon::TGG, 'W'),
         ]);
 
@@ -0,0 +1,379 @@
+use anyhow::Result;
+
+use crate::constraints::{ChangeScorer, SequenceConstraint, Violation};
+use crate::consts::VALID_NUCLEOTIDES;
+
+///
+/// Observed and expected frequency of a dinucleotide in a DNA sequence. Every adjacent pair
+/// of nucleotides is counted, inside codons (positions 1-2 and 2-3) as well as across codon
+/// boundaries (position 3 of one codon and 1 of the next).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DinucleotideStats {
+    pub dinucleotide: String,
+    pub observed: usize,
+    /// Count expected from the mononucleotide composition, `(N - 1) f(X) f(Y)`
+    pub expected: f64,
+    /// Observed/expected ratio, 0 when the dinucleotide isn't expected at all
+    pub ratio: f64,
+    /// Occurrences at codon positions 1-2 and 2-3
+    pub within_codons: usize,
+    /// Occurrences at codon positions 3-1
+    pub across_codons: usize,
+}
+
+fn base_index(base: u8) -> Option<usize> {
+    VALID_NUCLEOTIDES
+        .bytes()
+        .position(|b| b == base.to_ascii_uppercase())
+}
+
+///
+/// Compute observed/expected statistics for all 16 dinucleotides
+///
+/// # Arguments
+/// - dna sequence, assumed to be in frame
+///
+/// # Returns
+/// - the statistics, in alphabetical order (AA, AC, ... TT)
+///
+pub fn compute_dinucleotide_stats(dna: &str) -> Vec<DinucleotideStats> {
+    let bases = dna.as_bytes();
+    let mut mono = [0usize; 4];
+    let mut within = [[0usize; 4]; 4];
+    let mut across = [[0usize; 4]; 4];
+
+    for base in bases {
+        if let Some(i) = base_index(*base) {
+            mono[i] += 1;
+        }
+    }
+    for (position, pair) in bases.windows(2).enumerate() {
+        let (Some(x), Some(y)) = (base_index(pair[0]), base_index(pair[1])) else {
+            continue;
+        };
+        if position % 3 == 2 {
+            across[x][y] += 1;
+        } else {
+            within[x][y] += 1;
+        }
+    }
+
+    let total: usize = mono.iter().sum();
+    let pairs = total.saturating_sub(1) as f64;
+    let nucleotides: Vec<char> = VALID_NUCLEOTIDES.chars().collect();
+
+    let mut stats = Vec::with_capacity(16);
+    for x in 0..4 {
+        for y in 0..4 {
+            let observed = within[x][y] + across[x][y];
+            let expected = if total > 0 {
+                pairs * (mono[x] as f64 / total as f64) * (mono[y] as f64 / total as f64)
+            } else {
+                0.0
+            };
+            stats.push(DinucleotideStats {
+                dinucleotide: format!("{}{}", nucleotides[x], nucleotides[y]),
+                observed,
+                expected,
+                ratio: if expected > 0.0 {
+                    observed as f64 / expected
+                } else {
+                    0.0
+                },
+                within_codons: within[x][y],
+                across_codons: across[x][y],
+            });
+        }
+    }
+    stats
+}
+
+///
+/// Constraint on the observed/expected ratio of a dinucleotide, e.g. CpG or UpA depletion for
+/// innate immune evasion, or enrichment for attenuation. RNA notation (U) is accepted.
+///
+/// When a dinucleotide is too frequent, every occurrence is reported as a violation and
+/// shares the penalty, so each can be repaired on its own. When it is too rare there is no
+/// occurrence to point at, and a single violation spans the whole sequence.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DinucleotideConstraint {
+    dinucleotide: String,
+    min_ratio: Option<f64>,
+    max_ratio: Option<f64>,
+}
+
+impl DinucleotideConstraint {
+    fn new(
+        dinucleotide: &str,
+        min_ratio: Option<f64>,
+        max_ratio: Option<f64>,
+    ) -> Result<DinucleotideConstraint> {
+        let dinucleotide = dinucleotide.to_uppercase().replace('U', "T");
+        if dinucleotide.len() != 2 || !dinucleotide.chars().all(|c| VALID_NUCLEOTIDES.contains(c)) {
+            anyhow::bail!("Invalid dinucleotide: {dinucleotide}")
+        }
+        Ok(DinucleotideConstraint {
+            dinucleotide,
+            min_ratio,
+            max_ratio,
+        })
+    }
+
+    ///
+    /// Keep the observed/expected ratio of a dinucleotide at or below `max_ratio`
+    ///
+    pub fn deplete(dinucleotide: &str, max_ratio: f64) -> Result<DinucleotideConstraint> {
+        DinucleotideConstraint::new(dinucleotide, None, Some(max_ratio))
+    }
+
+    ///
+    /// Keep the observed/expected ratio of a dinucleotide at or above `min_ratio`
+    ///
+    pub fn enrich(dinucleotide: &str, min_ratio: f64) -> Result<DinucleotideConstraint> {
+        DinucleotideConstraint::new(dinucleotide, Some(min_ratio), None)
+    }
+}
+
+impl DinucleotideConstraint {
+    /// The dinucleotide, in uppercase DNA notation
+    pub fn dinucleotide(&self) -> &str {
+        &self.dinucleotide
+    }
+
+    /// Lowest observed/expected ratio allowed, if any
+    pub fn min_ratio(&self) -> Option<f64> {
+        self.min_ratio
+    }
+
+    /// Highest observed/expected ratio allowed, if any
+    pub fn max_ratio(&self) -> Option<f64> {
+        self.max_ratio
+    }
+
+    /// Number of times the dinucleotide occurs
+    fn occurrences(&self, bases: &[u8]) -> usize {
+        bases
+            .windows(2)
+            .filter(|pair| pair.eq_ignore_ascii_case(self.dinucleotide.as_bytes()))
+            .count()
+    }
+
+    /// How many occurrences too many (or too few) for the base composition
+    fn excess(&self, observed: usize, composition: &[usize; 4]) -> f64 {
+        let bases = self.dinucleotide.as_bytes();
+        let (Some(x), Some(y)) = (base_index(bases[0]), base_index(bases[1])) else {
+            return 0.0;
+        };
+        let total: usize = composition.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let expected = total.saturating_sub(1) as f64
+            * (composition[x] as f64 / total as f64)
+            * (composition[y] as f64 / total as f64);
+
+        let observed = observed as f64;
+        match (self.min_ratio, self.max_ratio) {
+            (_, Some(max_ratio)) if observed > max_ratio * expected => {
+                observed - max_ratio * expected
+            }
+            (Some(min_ratio), _) if observed < min_ratio * expected => {
+                min_ratio * expected - observed
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+fn base_composition(bases: &[u8]) -> [usize; 4] {
+    let mut composition = [0usize; 4];
+    for base in bases {
+        if let Some(i) = base_index(*base) {
+            composition[i] += 1;
+        }
+    }
+    composition
+}
+
+impl SequenceConstraint for DinucleotideConstraint {
+    fn name(&self) -> &str {
+        "dinucleotide"
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let bases = dna.as_bytes();
+        let observed = self.occurrences(bases);
+        let composition = base_composition(bases);
+        let penalty = self.excess(observed, &composition);
+        if penalty == 0.0 {
+            return vec![];
+        }
+
+        let Some(stats) = compute_dinucleotide_stats(dna)
+            .into_iter()
+            .find(|stats| stats.dinucleotide == self.dinucleotide)
+        else {
+            return vec![];
+        };
+        let description = format!(
+            "{} observed/expected ratio {:.3} outside {} - {}",
+            self.dinucleotide,
+            stats.ratio,
+            self.min_ratio
+                .map_or("-".to_string(), |r| format!("{r:.3}")),
+            self.max_ratio
+                .map_or("-".to_string(), |r| format!("{r:.3}")),
+        );
+
+        // too rare: nothing to point at
+        let too_frequent = self
+            .max_ratio
+            .is_some_and(|max_ratio| observed as f64 > max_ratio * stats.expected);
+        if !too_frequent {
+            return vec![Violation {
+                constraint: self.name().to_string(),
+                start: 0,
+                end: dna.len(),
+                penalty,
+                description,
+            }];
+        }
+
+        // too frequent: every occurrence takes an equal share of the penalty
+        bases
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair.eq_ignore_ascii_case(self.dinucleotide.as_bytes()))
+            .map(|(position, _)| Violation {
+                constraint: self.name().to_string(),
+                start: position,
+                end: position + 2,
+                penalty: penalty / observed as f64,
+                description: format!(
+                    "{} at position {position}, {description}",
+                    self.dinucleotide
+                ),
+            })
+            .collect()
+    }
+
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        // the penalty only depends on the base composition and the number of occurrences,
+        // so a change can be scored from the bases around it
+        let bases = dna.as_bytes();
+        let observed = self.occurrences(bases);
+        let composition = base_composition(bases);
+        let penalty = self.excess(observed, &composition);
+        Box::new(move |start, replacement| {
+            let end = start + replacement.len();
+            let (from, to) = (start.saturating_sub(1), (end + 1).min(bases.len()));
+            let changed = [&bases[from..start], replacement.as_bytes(), &bases[end..to]].concat();
+
+            let observed =
+                observed + self.occurrences(&changed) - self.occurrences(&bases[from..to]);
+            let mut changed_composition = composition;
+            for (i, count) in base_composition(replacement.as_bytes()).iter().enumerate() {
+                changed_composition[i] += count;
+            }
+            for (i, count) in base_composition(&bases[start..end]).iter().enumerate() {
+                changed_composition[i] -= count;
+            }
+            self.excess(observed, &changed_composition) - penalty
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use crate::constraints::enforce_constraints;
+    use crate::models::Codon;
+    use crate::optimizations::CodonUsageByResidue;
+    use crate::utils::split_into_codons;
+
+    #[rstest]
+    fn test_compute_dinucleotide_stats() {
+        // CG inside the first codon and across the boundary between the second and third
+        let stats = compute_dinucleotide_stats("ACGAACGTT");
+        assert_eq!(stats.len(), 16);
+
+        let cg = stats.iter().find(|s| s.dinucleotide == "CG").unwrap();
+        assert_eq!(cg.observed, 2);
+        assert_eq!(cg.within_codons, 1);
+        assert_eq!(cg.across_codons, 1);
+        // 8 pairs, 2/9 C and 2/9 G
+        assert_eq!((cg.expected - 8.0 * 4.0 / 81.0).abs() < 1e-9, true);
+        assert_eq!((cg.ratio - 2.0 / cg.expected).abs() < 1e-9, true);
+    }
+
+    #[rstest]
+    fn test_deplete_cpg() {
+        let usage: CodonUsageByResidue = HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.1),
+                    (Codon::GCC, 0.2),
+                    (Codon::GCA, 0.3),
+                    (Codon::GCG, 0.4),
+                ]),
+            ),
+            (
+                'T',
+                HashMap::from([
+                    (Codon::ACT, 0.1),
+                    (Codon::ACC, 0.2),
+                    (Codon::ACA, 0.3),
+                    (Codon::ACG, 0.4),
+                ]),
+            ),
+        ]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![Box::new(
+            DinucleotideConstraint::deplete("CG", 0.2).unwrap(),
+        )];
+
+        let mut codons = split_into_codons("GCGACGGCGACG").unwrap();
+        let report = enforce_constraints(&mut codons, &usage, &constraints, &[]);
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(report.violations, vec![]);
+        assert_eq!(constraints[0].find_violations(&dna), vec![]);
+        let cg = compute_dinucleotide_stats(&dna)
+            .into_iter()
+            .find(|s| s.dinucleotide == "CG")
+            .unwrap();
+        assert_eq!(cg.ratio <= 0.2, true);
+
+        // every CpG is reported on its own, sharing the penalty
+        let constraint = DinucleotideConstraint::deplete("CG", 0.2).unwrap();
+        let violations = constraint.find_violations("GCGACGGCGACG");
+        let spans: Vec<(usize, usize)> = violations.iter().map(|v| (v.start, v.end)).collect();
+        assert_eq!(spans, vec![(1, 3), (4, 6), (7, 9), (10, 12)]);
+        let penalty: f64 = violations.iter().map(|v| v.penalty).sum();
+        let excess = constraint.excess(4, &base_composition(b"GCGACGGCGACG"));
+        assert_eq!((penalty - excess).abs() < 1e-9, true);
+
+        // too few CpA is one violation over the whole sequence
+        let violations = DinucleotideConstraint::enrich("CA", 1.0)
+            .unwrap()
+            .find_violations("GCGACGGCGACG");
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].start, violations[0].end), (0, 12));
+
+        // UpA in RNA notation
+        assert_eq!(
+            DinucleotideConstraint::enrich("UA", 1.5)
+                .unwrap()
+                .dinucleotide(),
+            "TA"
+        );
+    }
+}
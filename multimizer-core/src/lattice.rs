@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::constraints::{SequenceConstraint, StrictConstraint};
+use crate::consts::SequenceType;
+use crate::models::Codon;
+use crate::motifs::{Motif, MotifConstraint, MotifPlacement};
+use crate::optimizations::{
+    finalize_optimization, resolve_locked_codons, CodonUsageByResidue, OptimizationOptions,
+    OptimizationResult,
+};
+use crate::restriction::RestrictionEnzyme;
+use crate::utils::{
+    detect_sequence_type, find_iupac_matches, normalize_codon_usage, reverse_complement,
+    translate_dna_sequence, validate_iupac_pattern,
+};
+
+/// Best path ending in a particular state: its score and where it came from
+struct PathEnd {
+    score: f64,
+    previous: Option<(String, Codon)>,
+}
+
+///
+/// Optimize a query sequence with a dynamic programming (Viterbi) search over the codon
+/// lattice of the protein, guaranteeing that none of the forbidden k-mers appear in the
+/// output. This sequence can be either protein or DNA.
+///
+/// The search maximizes the summed log codon preference, so without any forbidden k-mers
+/// it picks the most frequent codon for every residue. The state of the search is the last
+/// `k - 1` nucleotides (for the longest forbidden k-mer), which means k-mers spanning codon
+/// boundaries are handled exactly. Only the given strand is checked -- pass the reverse
+/// complement as well if both strands matter. The sites of `options.avoid_enzymes` and the
+/// forbidden motifs of `options.motifs` are forbidden as well.
+///
+/// The other constraints of `options` (e.g. GC content or required motifs) are enforced
+/// afterwards, but never at the cost of a forbidden k-mer -- a violation that can only be
+/// fixed by creating one is reported instead.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for organism
+/// - forbidden k-mers, with IUPAC nucleotide codes
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence, or an error if every possible sequence contains a forbidden k-mer
+///
+pub fn optimize_with_forbidden_kmers<S: AsRef<str>>(
+    query: &str,
+    codon_usage: &CodonUsageByResidue,
+    forbidden: &[S],
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+    let protein = match detect_sequence_type(query)? {
+        SequenceType::Dna => translate_dna_sequence(query)?,
+        SequenceType::Protein => query.to_string(),
+    };
+
+    let mut forbidden_kmers = forbidden
+        .iter()
+        .map(|kmer| validate_iupac_pattern(kmer.as_ref()))
+        .collect::<Result<Vec<String>>>()?;
+    for motif in &options.motifs {
+        if motif.placement != MotifPlacement::Forbidden {
+            continue;
+        }
+        // motifs can be built as struct literals, so their patterns aren't validated yet
+        let pattern = validate_iupac_pattern(&motif.pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid pattern for motif {}: {e}", motif.name))?;
+        if motif.both_strands {
+            forbidden_kmers.push(reverse_complement(&pattern));
+        }
+        forbidden_kmers.push(pattern);
+    }
+    for name in &options.avoid_enzymes {
+        let enzyme = RestrictionEnzyme::from_catalog(name)?;
+        forbidden_kmers.push(reverse_complement(&enzyme.site));
+        forbidden_kmers.push(enzyme.site);
+    }
+
+    // the constraint repair may not put any of the k-mers back in, whatever else it fixes
+    let mut constraints = options.build_constraints()?;
+    if !forbidden_kmers.is_empty() {
+        let motifs = forbidden_kmers
+            .iter()
+            .map(|kmer| Motif {
+                name: "forbidden k-mer".to_string(),
+                pattern: kmer.clone(),
+                placement: MotifPlacement::Forbidden,
+                both_strands: false,
+            })
+            .collect();
+        constraints
+            .push(Box::new(StrictConstraint(MotifConstraint { motifs }))
+                as Box<dyn SequenceConstraint>);
+    }
+    let context_length = forbidden_kmers
+        .iter()
+        .map(|kmer| kmer.len() - 1)
+        .max()
+        .unwrap_or(0);
+
+    let normalized_usage = normalize_codon_usage(codon_usage);
+
+    // layers[i] holds the best path for every state after placing codon i
+    let mut layers: Vec<HashMap<String, PathEnd>> = Vec::with_capacity(protein.len());
+    let mut previous_layer: HashMap<String, PathEnd> = HashMap::from([(
+        String::new(),
+        PathEnd {
+            score: 0.0,
+            previous: None,
+        },
+    )]);
+
+    for (i, residue) in protein.chars().enumerate() {
+        let Some(preferences) = normalized_usage.get(&residue) else {
+            anyhow::bail!("Invalid residue passed in: {residue}")
+        };
+        let mut candidates: Vec<(Codon, f64)> = match locked.get(i).copied().flatten() {
+            Some(codon) => vec![(codon, 0.0)],
+            None => preferences
+                .iter()
+                .map(|(codon, pref)| (*codon, pref.max(f64::MIN_POSITIVE).ln()))
+                .collect(),
+        };
+        candidates.sort_by_key(|(codon, _)| *codon);
+
+        // iterate the states in order so ties are broken the same way every time
+        let mut states: Vec<(&String, &PathEnd)> = previous_layer.iter().collect();
+        states.sort_by_key(|(state, _)| *state);
+
+        let mut layer: HashMap<String, PathEnd> = HashMap::new();
+        for (state, path) in states {
+            for (codon, log_pref) in &candidates {
+                let extended = format!("{state}{codon}");
+                if contains_new_forbidden_kmer(&extended, &forbidden_kmers) {
+                    continue;
+                }
+
+                let next_state =
+                    extended[extended.len().saturating_sub(context_length)..].to_string();
+                let score = path.score + log_pref;
+                let is_better = layer
+                    .get(&next_state)
+                    .is_none_or(|existing| score > existing.score);
+                if is_better {
+                    layer.insert(
+                        next_state,
+                        PathEnd {
+                            score,
+                            previous: Some((state.clone(), *codon)),
+                        },
+                    );
+                }
+            }
+        }
+
+        if layer.is_empty() {
+            anyhow::bail!(
+                "No valid sequence exists: every codon choice for residue {residue} at position {pos} creates a forbidden k-mer",
+                pos = i + 1
+            )
+        }
+
+        layers.push(std::mem::replace(&mut previous_layer, layer));
+    }
+    layers.push(previous_layer);
+
+    // trace back from the best final state
+    let mut codons: Vec<Codon> = Vec::with_capacity(protein.len());
+    let mut state = layers
+        .last()
+        .unwrap()
+        .iter()
+        .max_by(|(s1, p1), (s2, p2)| p1.score.total_cmp(&p2.score).then(s2.cmp(s1)))
+        .map(|(state, _)| state.clone())
+        .unwrap();
+
+    for layer in layers.iter().skip(1).rev() {
+        let (previous_state, codon) = layer[&state].previous.clone().unwrap();
+        codons.push(codon);
+        state = previous_state;
+    }
+    codons.reverse();
+
+    finalize_optimization(codons, codon_usage, &constraints, options, &locked, 1)
+}
+
+/// Does a forbidden k-mer end in the last codon (three nucleotides) of the sequence?
+fn contains_new_forbidden_kmer(seq: &str, forbidden_kmers: &[String]) -> bool {
+    let new_start = seq.len().saturating_sub(3);
+    forbidden_kmers.iter().any(|kmer| {
+        let earliest_start = new_start.saturating_sub(kmer.len() - 1);
+        seq.get(earliest_start..)
+            .is_some_and(|window| !find_iupac_matches(window, kmer).is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    use crate::constraints::GcContentConstraint;
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            ('E', HashMap::from([(Codon::GAA, 0.7), (Codon::GAG, 0.3)])),
+            ('F', HashMap::from([(Codon::TTT, 0.6), (Codon::TTC, 0.4)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_optimize_without_forbidden_kmers(usage: CodonUsageByResidue) {
+        let result =
+            optimize_with_forbidden_kmers::<&str>("EF", &usage, &[], &Default::default()).unwrap();
+        assert_eq!(result.seq, "GAATTT");
+    }
+
+    #[rstest]
+    fn test_optimize_with_kmer_across_codon_boundary(usage: CodonUsageByResidue) {
+        // GAATTC (EcoRI) spans the E and F codons
+        let result =
+            optimize_with_forbidden_kmers("EF", &usage, &["GAATTC", "AATT"], &Default::default())
+                .unwrap();
+        assert_eq!(result.seq, "GAGTTT");
+        assert_eq!(result.translated_seq, "EF");
+    }
+
+    #[rstest]
+    fn test_optimize_with_no_valid_sequence(usage: CodonUsageByResidue) {
+        let result = optimize_with_forbidden_kmers("EF", &usage, &["GA"], &Default::default());
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[rstest]
+    fn test_optimize_avoiding_enzymes(usage: CodonUsageByResidue) {
+        // GARTTT rules out TTT after either E codon, and EcoRI (GAATTC) rules out
+        // GAA before TTC, leaving only GAGTTC
+        let options = OptimizationOptions {
+            avoid_enzymes: vec!["EcoRI".to_string()],
+            ..Default::default()
+        };
+        let result = optimize_with_forbidden_kmers("EFEF", &usage, &["GARTTT"], &options).unwrap();
+        assert_eq!(result.seq, "GAGTTCGAGTTC");
+        assert_eq!(result.violations, vec![]);
+    }
+
+    #[rstest]
+    fn test_constraint_repair_keeps_kmers_out() {
+        let usage: CodonUsageByResidue =
+            HashMap::from([('R', HashMap::from([(Codon::AGA, 0.9), (Codon::CGC, 0.1)]))]);
+        let options = OptimizationOptions {
+            gc_content: Some(GcContentConstraint::new(1.0, 1.0).unwrap()),
+            ..Default::default()
+        };
+
+        // raising the GC content of both codons would create the forbidden k-mer
+        let result = optimize_with_forbidden_kmers("RR", &usage, &["CGCCGC"], &options).unwrap();
+        assert_eq!(result.seq.contains("CGCCGC"), false);
+        assert_eq!(
+            result
+                .violations
+                .iter()
+                .all(|v| v.constraint == "gc-content"),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_required_motif_keeps_kmers_out() {
+        let usage: CodonUsageByResidue =
+            HashMap::from([('L', HashMap::from([(Codon::CTT, 0.9), (Codon::TTG, 0.1)]))]);
+        let options = OptimizationOptions {
+            motifs: vec![Motif::required_at("start", "TTG", 0).unwrap()],
+            ..Default::default()
+        };
+
+        // TTG would fix both mismatches of the required motif, but creates the forbidden k-mer
+        let result = optimize_with_forbidden_kmers("L", &usage, &["TG"], &options).unwrap();
+        assert_eq!(result.seq, "CTT");
+        assert_eq!(result.violations.len(), 1);
+        assert_eq!(result.violations[0].constraint, "motif");
+    }
+
+    #[rstest]
+    fn test_empty_motif_pattern(usage: CodonUsageByResidue) {
+        let options = OptimizationOptions {
+            motifs: vec![Motif {
+                name: "empty".to_string(),
+                pattern: String::new(),
+                placement: MotifPlacement::Forbidden,
+                both_strands: true,
+            }],
+            ..Default::default()
+        };
+        let result = optimize_with_forbidden_kmers::<&str>("EF", &usage, &[], &options);
+        assert_eq!(result.is_err(), true);
+    }
+}
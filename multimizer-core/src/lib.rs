@@ -4,9 +4,25 @@
 //! Multimizer Core is a library that provides the core functionality of the Multimizer project -- a codon optimization toolkit.
 //!
 //!
+pub mod analysis;
+pub mod annealing;
+pub mod codon_pairs;
+pub mod constraints;
 pub mod consts;
+pub mod dinucleotides;
+pub mod lattice;
 pub mod models;
+pub mod motifs;
 pub mod optimizations;
+pub mod pareto;
+pub mod profile;
+pub mod repeats;
+pub mod restriction;
+pub mod sampling;
+pub mod signals;
+pub mod strategies;
+pub mod synthesis;
+pub mod tai;
 pub mod utils;
 
 #[cfg(feature = "sqlite")]
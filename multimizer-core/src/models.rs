@@ -21,8 +21,37 @@ pub struct Organism {
     pub gc3_perc: f32,
 }
 
+impl Organism {
+    ///
+    /// Whether the organism is a prokaryote (bacteria or archaea), judging by its division
+    /// or its translation table (11: bacterial, archaeal and plastid; 25: SR1 and gracilibacteria).
+    /// Plastids use table 11 too, so the table only counts for codon usage of a genome rather
+    /// than an organelle.
+    ///
+    pub fn is_prokaryote(&self) -> bool {
+        let division = self.division.to_lowercase();
+        let prokaryotic_division = matches!(division.as_str(), "bct" | "arc")
+            || ["bacteri", "archae", "prokaryot"]
+                .iter()
+                .any(|name| division.contains(name));
+        let organelle = self.organelle.to_lowercase();
+        let genomic = matches!(organelle.as_str(), "" | "genomic");
+
+        prokaryotic_division || (genomic && matches!(self.translation_table, 11 | 25))
+    }
+
+    ///
+    /// Whether the codon usage comes from the nuclear genome of a eukaryote, i.e. transcripts
+    /// are spliced and polyadenylated
+    ///
+    pub fn is_eukaryotic_nuclear(&self) -> bool {
+        let organelle = self.organelle.to_lowercase();
+        !self.is_prokaryote() && matches!(organelle.as_str(), "" | "genomic" | "nuclear")
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub enum Codon {
     AAA, AAC, AAG, AAT, ACA,
     ACC, ACG, ACT, AGA, AGC,
@@ -39,6 +68,30 @@ pub enum Codon {
     TTA, TTC, TTG, TTT,
 }
 
+impl Codon {
+    /// All 64 codons, in the same order as the enum
+    pub const ALL: [Codon; 64] = [
+        Codon::AAA, Codon::AAC, Codon::AAG, Codon::AAT, Codon::ACA,
+        Codon::ACC, Codon::ACG, Codon::ACT, Codon::AGA, Codon::AGC,
+        Codon::AGG, Codon::AGT, Codon::ATA, Codon::ATC, Codon::ATG,
+        Codon::ATT, Codon::CAA, Codon::CAC, Codon::CAG, Codon::CAT,
+        Codon::CCA, Codon::CCC, Codon::CCG, Codon::CCT, Codon::CGA,
+        Codon::CGC, Codon::CGG, Codon::CGT, Codon::CTA, Codon::CTC,
+        Codon::CTG, Codon::CTT, Codon::GAA, Codon::GAC, Codon::GAG,
+        Codon::GAT, Codon::GCA, Codon::GCC, Codon::GCG, Codon::GCT,
+        Codon::GGA, Codon::GGC, Codon::GGG, Codon::GGT, Codon::GTA,
+        Codon::GTC, Codon::GTG, Codon::GTT, Codon::TAA, Codon::TAC,
+        Codon::TAG, Codon::TAT, Codon::TCA, Codon::TCC, Codon::TCG,
+        Codon::TCT, Codon::TGA, Codon::TGC, Codon::TGG, Codon::TGT,
+        Codon::TTA, Codon::TTC, Codon::TTG, Codon::TTT,
+    ];
+
+    /// Position of the codon in `Codon::ALL`, handy for indexing fixed size tables
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
 impl TryFrom<&str> for Codon {
     type Error = String;
 
@@ -0,0 +1,321 @@
+use anyhow::Result;
+
+use crate::constraints::{ChangeScorer, SequenceConstraint, Violation};
+use crate::restriction::Strand;
+use crate::utils::{find_iupac_matches, iupac_matches, reverse_complement, validate_iupac_pattern};
+
+///
+/// Where a motif may (or must) occur
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotifPlacement {
+    /// The motif must not occur anywhere
+    Forbidden,
+    /// The motif must occur at this (0-based) nucleotide position
+    RequiredAt(usize),
+}
+
+///
+/// A user-defined sequence motif, written with IUPAC nucleotide codes
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motif {
+    pub name: String,
+    pub pattern: String,
+    pub placement: MotifPlacement,
+    /// Whether the reverse complement of the pattern counts as well
+    pub both_strands: bool,
+}
+
+///
+/// An occurrence of a motif in a DNA sequence. The position is always on the forward
+/// strand, even for matches on the reverse strand.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifMatch {
+    pub motif: String,
+    pub position: usize,
+    pub strand: Strand,
+}
+
+impl Motif {
+    ///
+    /// Create a motif that must not occur on either strand
+    ///
+    pub fn forbidden(name: &str, pattern: &str) -> Result<Motif> {
+        Ok(Motif {
+            name: name.to_string(),
+            pattern: validate_iupac_pattern(pattern)?,
+            placement: MotifPlacement::Forbidden,
+            both_strands: true,
+        })
+    }
+
+    ///
+    /// Create a motif that must occur at a position on the forward strand. Set `both_strands`
+    /// to accept the reverse complement at that position as well.
+    ///
+    pub fn required_at(name: &str, pattern: &str, position: usize) -> Result<Motif> {
+        Ok(Motif {
+            name: name.to_string(),
+            pattern: validate_iupac_pattern(pattern)?,
+            placement: MotifPlacement::RequiredAt(position),
+            both_strands: false,
+        })
+    }
+
+    /// The patterns to search for, forward strand first
+    fn strand_patterns(&self) -> Vec<(Strand, String)> {
+        let mut patterns = vec![(Strand::Forward, self.pattern.clone())];
+        let reverse = reverse_complement(&self.pattern);
+        if self.both_strands && reverse != self.pattern {
+            patterns.push((Strand::Reverse, reverse));
+        }
+        patterns
+    }
+
+    ///
+    /// Find every match of the motif in a DNA sequence
+    ///
+    /// # Arguments
+    /// - dna sequence
+    ///
+    /// # Returns
+    /// - the matches, forward strand first
+    ///
+    pub fn find_matches(&self, dna: &str) -> Vec<MotifMatch> {
+        self.strand_patterns()
+            .into_iter()
+            .flat_map(|(strand, pattern)| {
+                find_iupac_matches(dna, &pattern)
+                    .into_iter()
+                    .map(move |position| MotifMatch {
+                        motif: self.name.clone(),
+                        position,
+                        strand,
+                    })
+            })
+            .collect()
+    }
+
+    /// Fewest mismatches between the motif (on either allowed strand) and the sequence at a position
+    fn mismatches_at(&self, dna: &str, position: usize) -> usize {
+        let Some(window) = dna.as_bytes().get(position..position + self.pattern.len()) else {
+            return self.pattern.len();
+        };
+        self.strand_patterns()
+            .iter()
+            .map(|(_, pattern)| {
+                pattern
+                    .bytes()
+                    .zip(window)
+                    .filter(|(code, base)| !iupac_matches(*code, **base))
+                    .count()
+            })
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+///
+/// Find the matches of a set of motifs
+///
+/// # Arguments
+/// - dna sequence
+/// - the motifs
+///
+/// # Returns
+/// - every match, grouped by motif
+///
+pub fn find_motif_matches(dna: &str, motifs: &[Motif]) -> Vec<MotifMatch> {
+    motifs
+        .iter()
+        .flat_map(|motif| motif.find_matches(dna))
+        .collect()
+}
+
+///
+/// Constraint that keeps forbidden motifs out of the sequence and required motifs at their
+/// positions. A required motif that doesn't match is penalized by its number of mismatches.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifConstraint {
+    pub motifs: Vec<Motif>,
+}
+
+impl SequenceConstraint for MotifConstraint {
+    fn name(&self) -> &str {
+        "motif"
+    }
+
+    fn is_hard(&self) -> bool {
+        true
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let mut violations = vec![];
+        for motif in &self.motifs {
+            match motif.placement {
+                MotifPlacement::Forbidden => {
+                    violations.extend(motif.find_matches(dna).into_iter().map(|found| Violation {
+                        constraint: self.name().to_string(),
+                        start: found.position,
+                        end: found.position + motif.pattern.len(),
+                        penalty: 1.0,
+                        description: format!(
+                            "forbidden motif {} ({:?} strand) at position {}",
+                            motif.name, found.strand, found.position
+                        ),
+                    }))
+                }
+                MotifPlacement::RequiredAt(position) => {
+                    let mismatches = motif.mismatches_at(dna, position);
+                    if mismatches > 0 {
+                        violations.push(Violation {
+                            constraint: self.name().to_string(),
+                            start: position,
+                            end: (position + motif.pattern.len()).min(dna.len()),
+                            penalty: mismatches as f64,
+                            description: format!(
+                                "required motif {} missing at position {position} ({mismatches} mismatches)",
+                                motif.name
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        // required motifs sit at fixed positions, so keep track of where the region starts
+        let context = self
+            .motifs
+            .iter()
+            .map(|motif| motif.pattern.len())
+            .max()
+            .unwrap_or(0);
+        Box::new(move |start, replacement| {
+            let end = start + replacement.len();
+            let from = start.saturating_sub(context);
+            let to = (end + context).min(dna.len());
+            let penalty = |region: &str| -> f64 {
+                self.motifs
+                    .iter()
+                    .map(|motif| match motif.placement {
+                        MotifPlacement::Forbidden => motif.find_matches(region).len() as f64,
+                        MotifPlacement::RequiredAt(position) if position >= from => {
+                            motif.mismatches_at(region, position - from) as f64
+                        }
+                        MotifPlacement::RequiredAt(_) => 0.0,
+                    })
+                    .sum()
+            };
+            let changed = format!("{}{replacement}{}", &dna[from..start], &dna[end..to]);
+            penalty(&changed) - penalty(&dna[from..to])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    use crate::constraints::{enforce_constraints, GcWindowConstraint};
+    use crate::models::Codon;
+    use crate::optimizations::CodonUsageByResidue;
+    use crate::utils::split_into_codons;
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            (
+                'L',
+                HashMap::from([
+                    (Codon::TTA, 0.1),
+                    (Codon::TTG, 0.1),
+                    (Codon::CTT, 0.1),
+                    (Codon::CTC, 0.1),
+                    (Codon::CTA, 0.1),
+                    (Codon::CTG, 0.5),
+                ]),
+            ),
+            ('K', HashMap::from([(Codon::AAA, 0.7), (Codon::AAG, 0.3)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_find_motif_matches() {
+        // CTGRAA matches CTGAAA forward, and TTYCAG is found as its reverse complement
+        let motif = Motif::forbidden("primer", "CTGRAA").unwrap();
+        let matches = motif.find_matches("CTGAAAGGTTCCAG");
+
+        assert_eq!(
+            matches,
+            vec![
+                MotifMatch {
+                    motif: "primer".to_string(),
+                    position: 0,
+                    strand: Strand::Forward
+                },
+                MotifMatch {
+                    motif: "primer".to_string(),
+                    position: 8,
+                    strand: Strand::Reverse
+                },
+            ]
+        );
+        assert_eq!(Motif::forbidden("bad", "CTGXAA").is_err(), true);
+    }
+
+    #[rstest]
+    fn test_enforce_motifs(usage: CodonUsageByResidue) {
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![Box::new(MotifConstraint {
+            motifs: vec![
+                Motif::forbidden("primer", "CTGAAA").unwrap(),
+                Motif::required_at("tag", "TTAAAG", 6).unwrap(),
+            ],
+        })];
+
+        let mut codons = split_into_codons("CTGAAACTGAAA").unwrap();
+        let report = enforce_constraints(&mut codons, &usage, &constraints, &[]);
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(report.violations, vec![]);
+        assert_eq!(&dna[6..], "TTAAAG");
+        assert_eq!(dna.contains("CTGAAA"), false);
+    }
+
+    #[rstest]
+    fn test_motifs_come_before_gc() {
+        // the only way to raise the GC content creates the forbidden motif
+        let usage: CodonUsageByResidue =
+            HashMap::from([('A', HashMap::from([(Codon::GCT, 0.6), (Codon::GCC, 0.4)]))]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![
+            Box::new(GcWindowConstraint::new(3, 1.0, 1.0).unwrap()),
+            Box::new(MotifConstraint {
+                motifs: vec![Motif::forbidden("GC run", "GCCGCC").unwrap()],
+            }),
+        ];
+
+        let mut codons = split_into_codons("GCTGCC").unwrap();
+        let report = enforce_constraints(&mut codons, &usage, &constraints, &[]);
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(dna, "GCTGCC");
+        assert_eq!(report.changes, vec![]);
+        assert_eq!(
+            report
+                .violations
+                .iter()
+                .all(|v| v.constraint == "gc-window"),
+            true
+        );
+    }
+}
@@ -1,13 +1,28 @@
 use std::collections::HashMap;
+use std::ops::Range;
 
 use anyhow::Result;
 
 use crate::{
-    consts::SequenceType,
-    models::Codon,
+    constraints::{
+        enforce_constraints, CodonChange, GcContentConstraint, GcWindowConstraint,
+        LowComplexityConstraint, SequenceConstraint, Violation,
+    },
+    consts::{CodonToAA, SequenceType},
+    dinucleotides::DinucleotideConstraint,
+    models::{Codon, Organism},
+    motifs::{find_motif_matches, Motif, MotifConstraint, MotifMatch},
+    profile::{compute_adaptation_profile, AdaptationProfile, ProfileOptions},
+    repeats::RepeatConstraint,
+    restriction::RestrictionSiteConstraint,
+    sampling::CodonSampler,
+    signals::{PolyadenylationSignalConstraint, ShineDalgarnoConstraint, SpliceSiteConstraint},
+    strategies::{OptimizationStrategy, WeightedRandomStrategy},
     utils::{
-        compute_rca, compute_rca_xyz_table, detect_sequence_type,
-        select_random_codon_from_usage_table, translate_dna_sequence,
+        build_averaged_table, compute_cai, compute_codon_usage_error, compute_rca,
+        compute_rca_xyz_table_from_usage, compute_relative_adaptiveness, detect_sequence_type,
+        normalize_codon_usage, remove_prohibited_codons, split_into_codons, translate_dna_sequence,
+        validate_iupac_pattern, RCAxyzTable, RelativeAdaptivenessTable, MIN_RELATIVE_ADAPTIVENESS,
     },
 };
 
@@ -22,6 +37,58 @@ pub struct OptimizationOptions {
     pub seed: i32,
     pub prohibited_preference_threshold: f64,
     pub min_error: f64,
+    /// How codons are chosen for each candidate sequence
+    pub strategy: Box<dyn OptimizationStrategy>,
+    /// Weight of the codon pair score relative to codon usage (see `codon_pairs::optimize_with_codon_pairs`)
+    pub codon_pair_weight: f64,
+    /// Restriction enzymes (by name, see `restriction::RESTRICTION_ENZYME_CATALOG`) whose sites should be removed
+    pub avoid_enzymes: Vec<String>,
+    /// Allowed GC content of the whole sequence, see `GcContentConstraint::for_organism` to target a host
+    pub gc_content: Option<GcContentConstraint>,
+    /// Allowed GC content of every sliding window of the sequence
+    pub gc_window: Option<GcWindowConstraint>,
+    /// Limits on homopolymers and short tandem repeats
+    pub low_complexity: Option<LowComplexityConstraint>,
+    /// Longest direct or inverted repeat allowed, in nucleotides (see `repeats::find_repeats`)
+    pub max_repeat_length: Option<usize>,
+    /// Internal ribosome binding sites to remove, for bacterial hosts
+    pub shine_dalgarno: Option<ShineDalgarnoConstraint>,
+    /// Cryptic splice sites to remove, for eukaryotic hosts
+    pub splice_sites: Option<SpliceSiteConstraint>,
+    /// Polyadenylation signals to remove, for eukaryotic hosts
+    pub polyadenylation_signals: Option<PolyadenylationSignalConstraint>,
+    /// User-defined forbidden and required motifs, reported in `OptimizationResult::motif_matches`
+    pub motifs: Vec<Motif>,
+    /// Regions of the input DNA that are kept exactly as given
+    pub locked_regions: Vec<LockedRegion>,
+    /// Limits on dinucleotide observed/expected ratios, e.g. CpG depletion
+    pub dinucleotides: Vec<DinucleotideConstraint>,
+    /// Relative adaptiveness weights `OptimizationResult::cai_value` is computed with, e.g. from
+    /// highly expressed genes (see `compute_relative_adaptiveness_from_sequences`). Derived from
+    /// the target codon usage when not set.
+    pub cai_reference: Option<RelativeAdaptivenessTable>,
+    /// RCAxyz table `OptimizationResult::rca_value` is computed with, ideally from the raw codon
+    /// counts of the host (see `compute_rca_xyz_table`). Derived from the target codon usage
+    /// when not set, except for multi-organism optimizations: the averaged usage table is
+    /// normalized per residue, so their rca is the mean of `OptimizationResult::organism_scores`
+    /// instead.
+    pub rca_reference: Option<RCAxyzTable>,
+    /// RCAxyz tables of the hosts of a multi-organism optimization, by organism id, that
+    /// `OptimizationResult::organism_scores` are computed with. Hosts without one are scored
+    /// against a table derived from their codon usage.
+    pub host_rca_references: HashMap<i32, RCAxyzTable>,
+}
+
+///
+/// A region of the input sequence that must not be changed by the optimizer. Ranges are
+/// 0-based and end-exclusive.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum LockedRegion {
+    /// Nucleotide range -- every codon it touches is locked
+    Nucleotides(Range<usize>),
+    /// Residue (codon) range
+    Residues(Range<usize>),
 }
 
 impl Default for OptimizationOptions {
@@ -31,21 +98,319 @@ impl Default for OptimizationOptions {
             seed: 42,
             prohibited_preference_threshold: 0.1,
             min_error: 0.01,
+            strategy: Box::new(WeightedRandomStrategy),
+            codon_pair_weight: 1.0,
+            avoid_enzymes: vec![],
+            gc_content: None,
+            gc_window: None,
+            low_complexity: None,
+            max_repeat_length: None,
+            shine_dalgarno: None,
+            splice_sites: None,
+            polyadenylation_signals: None,
+            motifs: vec![],
+            locked_regions: vec![],
+            dinucleotides: vec![],
+            cai_reference: None,
+            rca_reference: None,
+            host_rca_references: HashMap::new(),
         }
     }
 }
 
+impl OptimizationOptions {
+    ///
+    /// Enable the constraints that make sense for a host organism, unless they were already
+    /// set: internal Shine-Dalgarno avoidance for prokaryotes, cryptic splice site and polyA
+    /// signal avoidance for the nuclear genes of eukaryotes.
+    ///
+    /// # Arguments
+    /// - the host organism
+    ///
+    /// # Returns
+    /// - the updated options
+    ///
+    pub fn with_host_defaults(mut self, organism: &Organism) -> Self {
+        if organism.is_prokaryote() && self.shine_dalgarno.is_none() {
+            self.shine_dalgarno = Some(ShineDalgarnoConstraint::default());
+        }
+        if organism.is_eukaryotic_nuclear() {
+            if self.splice_sites.is_none() {
+                self.splice_sites = Some(SpliceSiteConstraint::default());
+            }
+            if self.polyadenylation_signals.is_none() {
+                self.polyadenylation_signals = Some(PolyadenylationSignalConstraint::default());
+            }
+        }
+        self
+    }
+
+    ///
+    /// Build the sequence constraints requested by the options
+    ///
+    /// # Returns
+    /// - the constraints, or an error if the options are invalid (e.g. an unknown enzyme)
+    ///
+    pub fn build_constraints(&self) -> Result<Vec<Box<dyn SequenceConstraint>>> {
+        let mut constraints: Vec<Box<dyn SequenceConstraint>> = vec![];
+
+        if !self.avoid_enzymes.is_empty() {
+            constraints.push(Box::new(RestrictionSiteConstraint::from_names(
+                &self.avoid_enzymes,
+            )?));
+        }
+        if let Some(gc) = &self.gc_content {
+            constraints.push(Box::new(GcContentConstraint::new(gc.min, gc.max)?));
+        }
+        if let Some(gc) = &self.gc_window {
+            constraints.push(Box::new(GcWindowConstraint::new(
+                gc.window_size,
+                gc.min,
+                gc.max,
+            )?));
+        }
+        if let Some(low_complexity) = &self.low_complexity {
+            constraints.push(Box::new(low_complexity.clone()));
+        }
+        if let Some(max_length) = self.max_repeat_length {
+            constraints.push(Box::new(RepeatConstraint { max_length }));
+        }
+        if let Some(shine_dalgarno) = &self.shine_dalgarno {
+            constraints.push(Box::new(shine_dalgarno.clone()));
+        }
+        if let Some(splice_sites) = &self.splice_sites {
+            constraints.push(Box::new(splice_sites.clone()));
+        }
+        if let Some(polyadenylation_signals) = &self.polyadenylation_signals {
+            constraints.push(Box::new(polyadenylation_signals.clone()));
+        }
+        if !self.motifs.is_empty() {
+            let motifs = self
+                .motifs
+                .iter()
+                .map(|motif| {
+                    Ok(Motif {
+                        pattern: validate_iupac_pattern(&motif.pattern)?,
+                        ..motif.clone()
+                    })
+                })
+                .collect::<Result<Vec<Motif>>>()?;
+            constraints.push(Box::new(MotifConstraint { motifs }));
+        }
+        for dinucleotide in &self.dinucleotides {
+            constraints.push(Box::new(dinucleotide.clone()));
+        }
+
+        Ok(constraints)
+    }
+}
+
 pub struct OptimizationResult {
     pub seq: String,
     pub iterations: i32,
     pub translated_seq: String,
+    /// Relative codon adaptation of the sequence against the target usage (see `compute_rca`).
+    /// For multi-organism optimizations without `OptimizationOptions::rca_reference`, the mean
+    /// of `organism_scores` weighted like the hosts.
     pub rca_value: f64,
+    /// Codon adaptation index of the sequence (see `compute_cai`)
+    pub cai_value: f64,
+    /// Deviation of the realized codon usage from the target usage table (see `compute_codon_usage_error`)
+    pub error: f64,
+    /// RCA of the optimized sequence against each organism, keyed by org_id.
+    /// Empty for single organism optimizations.
+    pub organism_scores: HashMap<i32, f64>,
+    /// Constraint violations that could not be removed from the sequence
+    pub violations: Vec<Violation>,
+    /// Synonymous substitutions made to satisfy the constraints
+    pub changes: Vec<CodonChange>,
+    /// Matches of the motifs in `OptimizationOptions::motifs`
+    pub motif_matches: Vec<MotifMatch>,
+}
+
+impl OptimizationResult {
+    ///
+    /// Sliding-window adaptation profile of the optimized sequence, to compare against the
+    /// profile of the input (see `compute_adaptation_profile`)
+    ///
+    pub fn adaptation_profile(
+        &self,
+        codon_usage: &CodonUsageByResidue,
+        options: &ProfileOptions,
+    ) -> Result<AdaptationProfile> {
+        compute_adaptation_profile(&self.seq, codon_usage, options)
+    }
+}
+
+///
+/// Resolve the locked regions of the options against the query
+///
+/// # Arguments
+/// - query seq, which must be DNA if any regions are locked
+/// - the locked regions
+///
+/// # Returns
+/// - the input codon for every locked position and None for the rest, or an empty vector
+///   when nothing is locked
+///
+pub(crate) fn resolve_locked_codons(
+    query: &str,
+    regions: &[LockedRegion],
+) -> Result<Vec<Option<Codon>>> {
+    if regions.is_empty() {
+        return Ok(vec![]);
+    }
+    if detect_sequence_type(query)? != SequenceType::Dna {
+        anyhow::bail!("Locked regions can only be kept for DNA queries")
+    }
+
+    let input_codons = split_into_codons(query)?;
+    let mut locked: Vec<Option<Codon>> = vec![None; input_codons.len()];
+    for region in regions {
+        let codon_range = match region {
+            LockedRegion::Nucleotides(range) => range.start / 3..range.end.div_ceil(3),
+            LockedRegion::Residues(range) => range.clone(),
+        };
+        if codon_range.start >= codon_range.end || codon_range.end > input_codons.len() {
+            anyhow::bail!("Locked region {region:?} is empty or outside of the query sequence")
+        }
+        for position in codon_range {
+            locked[position] = Some(input_codons[position]);
+        }
+    }
+
+    Ok(locked)
+}
+
+/// Put the locked input codons back in place
+pub(crate) fn apply_locked_codons(codons: &mut [Codon], locked: &[Option<Codon>]) {
+    for (codon, locked_codon) in codons.iter_mut().zip(locked) {
+        if let Some(locked_codon) = locked_codon {
+            *codon = *locked_codon;
+        }
+    }
+}
+
+///
+/// Enforce the sequence constraints on an optimized sequence and score it against the
+/// codon usage data. This is the last step of every optimizer.
+///
+/// # Arguments
+/// - the optimized codons
+/// - codon usage data the sequence was optimized for
+/// - constraints to enforce
+/// - options for the optimization algorithm, for the motifs to report matches for and the
+///   cai and rca references
+/// - locked codons (see `resolve_locked_codons`), which are never changed
+/// - number of iterations the optimizer ran for
+///
+/// # Returns
+/// - the optimization result
+///
+pub(crate) fn finalize_optimization(
+    mut codons: Vec<Codon>,
+    codon_usage: &CodonUsageByResidue,
+    constraints: &[Box<dyn SequenceConstraint>],
+    options: &OptimizationOptions,
+    locked: &[Option<Codon>],
+    iterations: i32,
+) -> Result<OptimizationResult> {
+    apply_locked_codons(&mut codons, locked);
+    let report = enforce_constraints(&mut codons, codon_usage, constraints, locked);
+
+    let optimized_sequence: String = codons.iter().map(|codon| codon.to_string()).collect();
+    let rca = match &options.rca_reference {
+        Some(table) => compute_rca(&optimized_sequence, table)?,
+        None => compute_rca(
+            &optimized_sequence,
+            &compute_rca_xyz_table_from_usage(codon_usage),
+        )?,
+    };
+    let cai = match &options.cai_reference {
+        Some(weights) => compute_cai(&optimized_sequence, weights)?,
+        None => compute_cai(
+            &optimized_sequence,
+            &compute_relative_adaptiveness(codon_usage),
+        )?,
+    };
+    let error = compute_codon_usage_error(&optimized_sequence, codon_usage)?;
+    let translated_seq = translate_dna_sequence(&optimized_sequence)?;
+    let motif_matches = find_motif_matches(&optimized_sequence, &options.motifs);
+
+    Ok(OptimizationResult {
+        seq: optimized_sequence,
+        translated_seq,
+        iterations,
+        rca_value: rca,
+        cai_value: cai,
+        error,
+        organism_scores: HashMap::new(),
+        violations: report.violations,
+        changes: report.changes,
+        motif_matches,
+    })
+}
+
+///
+/// Score a sequence against every host of a multi-organism optimization
+///
+/// # Arguments
+/// - dna sequence
+/// - codon usage data by organism, as given -- per residue normalization distorts the rca
+/// - options for the optimization algorithm, for `host_rca_references`
+///
+/// # Returns
+/// - the rca of the sequence for every host, by organism id
+///
+fn score_hosts(
+    dna: &str,
+    usage_by_org: &CodonUsageByResidueByOrganism,
+    options: &OptimizationOptions,
+) -> Result<HashMap<i32, f64>> {
+    let mut scores = HashMap::new();
+    for (org_id, usage) in usage_by_org {
+        let rca = match options.host_rca_references.get(org_id) {
+            Some(table) => compute_rca(dna, table)?,
+            None => compute_rca(dna, &compute_rca_xyz_table_from_usage(usage))?,
+        };
+        scores.insert(*org_id, rca);
+    }
+    Ok(scores)
+}
+
+///
+/// Average the rca of every host of a multi-organism optimization
+///
+/// # Arguments
+/// - the rca of every host, by organism id
+/// - weights for each organism, keyed by org_id
+///
+/// # Returns
+/// - the weighted mean, or the plain mean if the weights add up to zero
+///
+fn mean_host_rca(scores: &HashMap<i32, f64>, weights: &SpeciesWeights) -> f64 {
+    let total_weight: f64 = scores
+        .keys()
+        .map(|org_id| weights.get(org_id).copied().unwrap_or(0.0))
+        .sum();
+    if total_weight <= 0.0 {
+        return scores.values().sum::<f64>() / scores.len().max(1) as f64;
+    }
+    scores
+        .iter()
+        .map(|(org_id, rca)| rca * weights.get(org_id).copied().unwrap_or(0.0))
+        .sum::<f64>()
+        / total_weight
 }
 
 ///
 /// Optimize a query sequence for a particular organism. This sequence
 /// can be either protein or DNA.
 ///
+/// Candidates are generated until one deviates from the target usage by at most
+/// `options.min_error`, or until `options.max_iterations` is reached. The candidate
+/// with the lowest error is returned. Candidates are generated by `options.strategy`.
+///
 /// # Arguments
 /// - query seq
 /// - codon usage data for organism
@@ -59,9 +424,10 @@ pub fn optimize_for_single_organism(
     codon_usage: &CodonUsageByResidue,
     options: &OptimizationOptions,
 ) -> Result<OptimizationResult> {
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+
     // detect sequence type, translate if necessary
     let seq_type = detect_sequence_type(query)?;
-    let rca_xyz_table = compute_rca_xyz_table(codon_usage);
     let query = match seq_type {
         SequenceType::Dna => {
             // otherwise translate the sequence
@@ -71,23 +437,390 @@ pub fn optimize_for_single_organism(
         SequenceType::Protein => query.to_string(),
     };
 
-    let mut optimized_sequence = String::new();
+    let constraints = options.build_constraints()?;
+    let mut best: Option<(Vec<Codon>, f64)> = None;
+    let mut iterations = 0;
+
+    // keep generating candidates until one is close enough to the target usage
+    // or we run out of iterations, holding on to the best one we've seen
+    let mut sampler = CodonSampler::new(codon_usage, options.seed as u64)?;
 
-    for residue in query.chars() {
-        let random_codon =
-            select_random_codon_from_usage_table(residue, codon_usage, Some(options.seed))?;
-        optimized_sequence.push_str(&random_codon.to_string());
+    for _ in 0..options.max_iterations.max(1) {
+        iterations += 1;
+
+        let mut candidate = options
+            .strategy
+            .generate(&query, codon_usage, &mut sampler)?;
+        apply_locked_codons(&mut candidate, &locked);
+        let candidate_seq: String = candidate.iter().map(|codon| codon.to_string()).collect();
+
+        let error = compute_codon_usage_error(&candidate_seq, codon_usage)?;
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_error)| error < *best_error)
+        {
+            best = Some((candidate, error));
+        }
+
+        // deterministic strategies would just produce the same candidate again
+        if error <= options.min_error || options.strategy.is_deterministic() {
+            break;
+        }
     }
 
-    let rca = compute_rca(&optimized_sequence, &rca_xyz_table)?;
-    let translated_seq = translate_dna_sequence(&optimized_sequence)?;
+    let (codons, _) = best.unwrap();
 
-    Ok(OptimizationResult {
-        seq: optimized_sequence,
-        translated_seq,
-        iterations: 1,
-        rca_value: rca,
-    })
+    finalize_optimization(
+        codons,
+        codon_usage,
+        &constraints,
+        options,
+        &locked,
+        iterations,
+    )
+}
+
+///
+/// Optimize a query sequence for multiple organisms at once. This sequence
+/// can be either protein or DNA.
+///
+/// Codons that fall below `options.prohibited_preference_threshold` in *any* organism
+/// are removed, the remaining usage data is averaged according to the species weights,
+/// and the query is optimized against the averaged table. The result is then scored
+/// against each organism individually, and its rca is the weighted mean of those scores
+/// unless `options.rca_reference` is set.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for each organism, keyed by org_id
+/// - weights for each organism, keyed by org_id
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence, with the per-organism scores
+///
+pub fn optimize_for_multiple_organisms(
+    query: &str,
+    usage_by_org: &CodonUsageByResidueByOrganism,
+    weights: &SpeciesWeights,
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    if usage_by_org.is_empty() {
+        anyhow::bail!("No codon usage data was provided for optimization!")
+    }
+
+    for org_id in usage_by_org.keys() {
+        match weights.get(org_id) {
+            Some(weight) if *weight < 0.0 => {
+                anyhow::bail!("Negative weight {weight} given for organism {org_id}")
+            }
+            Some(_) => {}
+            None => anyhow::bail!("No weight was provided for organism {org_id}"),
+        }
+    }
+
+    let normalized_usage: CodonUsageByResidueByOrganism = usage_by_org
+        .iter()
+        .map(|(org_id, usage)| (*org_id, normalize_codon_usage(usage)))
+        .collect();
+
+    let corrected_usage =
+        remove_prohibited_codons(&normalized_usage, options.prohibited_preference_threshold)?;
+    let averaged_table = build_averaged_table(&corrected_usage, weights);
+
+    let mut result = optimize_for_single_organism(query, &averaged_table, options)?;
+
+    result.organism_scores = score_hosts(&result.seq, usage_by_org, options)?;
+    if options.rca_reference.is_none() {
+        result.rca_value = mean_host_rca(&result.organism_scores, weights);
+    }
+
+    Ok(result)
+}
+
+///
+/// Optimize a query sequence for the worst-off of multiple organisms. This sequence
+/// can be either protein or DNA.
+///
+/// Rather than averaging usage data (which can still leave one host poorly served), this
+/// maximizes the *minimum* codon adaptation index (see `compute_cai`) across all organisms.
+/// Prohibited codons are removed as in `optimize_for_multiple_organisms`. The search starts
+/// from the codon with the best worst-case adaptiveness at every position and then sweeps
+/// over the sequence, changing any codon that raises the minimum, until a sweep makes no
+/// changes or `options.max_iterations` sweeps have been done.
+///
+/// A codon missing from the usage data of an organism is scored with the lowest relative
+/// adaptiveness (`MIN_RELATIVE_ADAPTIVENESS`) for that organism. The rca of the result is
+/// the mean of the per-organism scores unless `options.rca_reference` is set.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for each organism, keyed by org_id
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence, with the per-organism scores
+///
+pub fn optimize_for_worst_host(
+    query: &str,
+    usage_by_org: &CodonUsageByResidueByOrganism,
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    if usage_by_org.is_empty() {
+        anyhow::bail!("No codon usage data was provided for optimization!")
+    }
+
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+    let protein = match detect_sequence_type(query)? {
+        SequenceType::Dna => translate_dna_sequence(query)?,
+        SequenceType::Protein => query.to_string(),
+    };
+
+    let constraints = options.build_constraints()?;
+    let mut org_ids: Vec<i32> = usage_by_org.keys().copied().collect();
+    org_ids.sort();
+
+    let normalized_usage: CodonUsageByResidueByOrganism = usage_by_org
+        .iter()
+        .map(|(org_id, usage)| (*org_id, normalize_codon_usage(usage)))
+        .collect();
+    let corrected_usage =
+        remove_prohibited_codons(&normalized_usage, options.prohibited_preference_threshold)?;
+
+    let equal_weights: SpeciesWeights = org_ids
+        .iter()
+        .map(|org_id| (*org_id, 1.0 / org_ids.len() as f64))
+        .collect();
+    let averaged_table = build_averaged_table(&corrected_usage, &equal_weights);
+
+    // log relative adaptiveness of every codon, for every organism (in org_ids order)
+    let log_adaptiveness: Vec<HashMap<Codon, f64>> = org_ids
+        .iter()
+        .map(|org_id| {
+            compute_relative_adaptiveness(&normalized_usage[org_id])
+                .into_iter()
+                .map(|(codon, w)| (codon, w.ln()))
+                .collect()
+        })
+        .collect();
+    // codons missing from the table of a host get the lowest weight, residues a host has no
+    // data for at all don't count towards its score
+    let codon_to_aa = CodonToAA::new();
+    let log_w = |org: usize, codon: &Codon| {
+        let residue = codon_to_aa.convert(codon)?;
+        let preferences = normalized_usage[&org_ids[org]].get(&residue)?;
+        if !preferences.contains_key(codon) {
+            return Some(MIN_RELATIVE_ADAPTIVENESS.ln());
+        }
+        // single codon residues are left out of the adaptiveness table
+        Some(log_adaptiveness[org].get(codon).copied().unwrap_or(0.0))
+    };
+
+    // allowed codons for every position, sorted so the search is deterministic
+    let mut lattice: Vec<Vec<Codon>> = Vec::with_capacity(protein.len());
+    for (position, residue) in protein.chars().enumerate() {
+        let Some(preferences) = averaged_table.get(&residue) else {
+            anyhow::bail!("Invalid residue passed in: {residue}")
+        };
+        if let Some(codon) = locked.get(position).copied().flatten() {
+            lattice.push(vec![codon]);
+            continue;
+        }
+        let mut codons: Vec<Codon> = preferences.keys().copied().collect();
+        codons.sort();
+        if codons.is_empty() {
+            anyhow::bail!("No codons available for residue {residue}")
+        }
+        lattice.push(codons);
+    }
+
+    // per-organism sum of log adaptiveness, and the number of codons that count towards it
+    let mut totals = vec![0.0; org_ids.len()];
+    let mut counts = vec![0usize; org_ids.len()];
+    let worst_case = |codon: &Codon| {
+        (0..org_ids.len())
+            .filter_map(|org| log_w(org, codon))
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    let mut codons: Vec<Codon> = Vec::with_capacity(lattice.len());
+    for candidates in &lattice {
+        let best = *candidates
+            .iter()
+            .max_by(|a, b| worst_case(a).total_cmp(&worst_case(b)).then(b.cmp(a)))
+            .unwrap();
+        for (org, total) in totals.iter_mut().enumerate() {
+            if let Some(w) = log_w(org, &best) {
+                *total += w;
+                counts[org] += 1;
+            }
+        }
+        codons.push(best);
+    }
+
+    let min_score = |totals: &[f64]| {
+        totals
+            .iter()
+            .zip(&counts)
+            .map(|(total, count)| {
+                if *count > 0 {
+                    total / *count as f64
+                } else {
+                    0.0
+                }
+            })
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    let mut iterations = 0;
+    for _ in 0..options.max_iterations.max(1) {
+        iterations += 1;
+        let mut improved = false;
+
+        for (position, candidates) in lattice.iter().enumerate() {
+            let current = codons[position];
+            let mut best_totals = totals.clone();
+            let mut best_score = min_score(&totals);
+
+            for candidate in candidates {
+                if *candidate == current {
+                    continue;
+                }
+                let new_totals: Vec<f64> = totals
+                    .iter()
+                    .enumerate()
+                    .map(|(org, total)| {
+                        total - log_w(org, &current).unwrap_or(0.0)
+                            + log_w(org, candidate).unwrap_or(0.0)
+                    })
+                    .collect();
+                let score = min_score(&new_totals);
+                if score > best_score + 1e-12 {
+                    best_score = score;
+                    best_totals = new_totals;
+                    codons[position] = *candidate;
+                }
+            }
+
+            if codons[position] != current {
+                totals = best_totals;
+                improved = true;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    let mut result = finalize_optimization(
+        codons,
+        &averaged_table,
+        &constraints,
+        options,
+        &locked,
+        iterations,
+    )?;
+
+    result.organism_scores = score_hosts(&result.seq, usage_by_org, options)?;
+    if options.rca_reference.is_none() {
+        result.rca_value = mean_host_rca(&result.organism_scores, &equal_weights);
+    }
+
+    Ok(result)
+}
+
+///
+/// Harmonize a DNA sequence from its native (source) organism to a target organism.
+///
+/// Instead of maximizing adaptation, harmonization tries to reproduce the translation
+/// rhythm of the native host: each codon is replaced by the codon that has the same
+/// relative usage rank in the target organism as the native codon has in the source
+/// organism. Rare codons stay rare and common codons stay common, which preserves slow
+/// translation regions that can matter for co-translational folding.
+///
+/// When the source and target organisms have a different number of codons for a residue
+/// (e.g. after removing prohibited codons), the rank is scaled to the size of the target
+/// codon family. Native codons that are missing from the source table are treated as the
+/// rarest codon for their residue.
+///
+/// # Arguments
+/// - query dna seq
+/// - codon usage data for the source organism
+/// - codon usage data for the target organism
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - harmonized sequence
+///
+pub fn harmonize_for_organism(
+    query: &str,
+    source_usage: &CodonUsageByResidue,
+    target_usage: &CodonUsageByResidue,
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    if detect_sequence_type(query)? != SequenceType::Dna {
+        anyhow::bail!("Harmonization requires a DNA sequence as input")
+    }
+
+    let constraints = options.build_constraints()?;
+    let locked = resolve_locked_codons(query, &options.locked_regions)?;
+    let translated_query = translate_dna_sequence(query)?;
+    let source_ranks = rank_codons_by_usage(source_usage);
+    let target_ranks = rank_codons_by_usage(target_usage);
+
+    let mut harmonized_codons: Vec<Codon> = Vec::with_capacity(translated_query.len());
+
+    for (residue, native_codon) in translated_query.chars().zip(query.as_bytes().chunks(3)) {
+        let native_codon =
+            Codon::try_from(std::str::from_utf8(native_codon)?).map_err(|e| anyhow::anyhow!(e))?;
+
+        let Some(source_codons) = source_ranks.get(&residue) else {
+            anyhow::bail!("No usage data for residue {residue} in the source organism")
+        };
+        let Some(target_codons) = target_ranks.get(&residue) else {
+            anyhow::bail!("No usage data for residue {residue} in the target organism")
+        };
+
+        let source_rank = source_codons
+            .iter()
+            .position(|codon| *codon == native_codon)
+            .unwrap_or(source_codons.len().saturating_sub(1));
+
+        let target_rank = if source_codons.len() > 1 {
+            let relative_rank = source_rank as f64 / (source_codons.len() - 1) as f64;
+            (relative_rank * (target_codons.len() - 1) as f64).round() as usize
+        } else {
+            0
+        };
+
+        harmonized_codons.push(target_codons[target_rank]);
+    }
+
+    finalize_optimization(
+        harmonized_codons,
+        target_usage,
+        &constraints,
+        options,
+        &locked,
+        1,
+    )
+}
+
+///
+/// Order the codons of every residue from most to least used.
+/// Ties are broken by codon order so the ranking is stable.
+///
+fn rank_codons_by_usage(usage: &CodonUsageByResidue) -> HashMap<char, Vec<Codon>> {
+    usage
+        .iter()
+        .filter(|(_, preferences)| !preferences.is_empty())
+        .map(|(aa, preferences)| {
+            let mut ranked: Vec<(Codon, f64)> = preferences.iter().map(|(c, p)| (*c, *p)).collect();
+            ranked.sort_by(|(c1, p1), (c2, p2)| p2.total_cmp(p1).then(c1.cmp(c2)));
+            (*aa, ranked.into_iter().map(|(codon, _)| codon).collect())
+        })
+        .collect()
 }
 
 ///
@@ -102,10 +835,11 @@ pub fn optimize_seq_test(query: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::strategies::MostFrequentCodonStrategy;
+    use crate::utils::compute_gc_content;
 
-    // use pretty_assertions::assert_eq;
-    // use rstest::{fixture, rstest};
-    use rstest::fixture;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
 
     // const EPSILON: f64 = 1e-6;
 
@@ -114,7 +848,7 @@ mod tests {
     // }
 
     #[fixture]
-    fn org_usage1() -> HashMap<char, HashMap<Codon, f32>> {
+    fn org_usage1() -> HashMap<char, HashMap<Codon, f64>> {
         HashMap::from([
             (
                 'A',
@@ -138,7 +872,7 @@ mod tests {
     }
 
     #[fixture]
-    fn org_usage2() -> HashMap<char, HashMap<Codon, f32>> {
+    fn org_usage2() -> HashMap<char, HashMap<Codon, f64>> {
         HashMap::from([
             (
                 'A',
@@ -165,4 +899,339 @@ mod tests {
     fn org_weights() -> SpeciesWeights {
         HashMap::from([(1, 0.33), (2, 0.67)])
     }
+
+    #[rstest]
+    fn test_optimize_for_single_organism_iterations(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+    ) {
+        let options = OptimizationOptions {
+            max_iterations: 25,
+            min_error: 0.0,
+            ..Default::default()
+        };
+
+        let result = optimize_for_single_organism("ARARARARAR", &org_usage1, &options).unwrap();
+
+        assert_eq!(result.translated_seq, "ARARARARAR");
+        assert_eq!(result.iterations, 25);
+        let error = compute_codon_usage_error(&result.seq, &org_usage1).unwrap();
+        assert_eq!((result.error - error).abs() < 1e-9, true);
+
+        // a generous min_error should stop at the very first candidate
+        let options = OptimizationOptions {
+            min_error: 1.0,
+            ..Default::default()
+        };
+        let result = optimize_for_single_organism("ARARARARAR", &org_usage1, &options).unwrap();
+        assert_eq!(result.iterations, 1);
+    }
+
+    #[rstest]
+    fn test_optimize_with_deterministic_strategy(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        let options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            ..Default::default()
+        };
+
+        let result = optimize_for_single_organism("ARAR", &org_usage1, &options).unwrap();
+
+        assert_eq!(result.seq, "GCGCGGGCGCGG");
+        assert_eq!(result.iterations, 1);
+
+        // the rca is scored against the given reference instead of the usage table
+        let options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            rca_reference: Some(Codon::ALL.iter().map(|codon| (*codon, 2.0)).collect()),
+            ..Default::default()
+        };
+        let result = optimize_for_single_organism("ARAR", &org_usage1, &options).unwrap();
+        assert_eq!((result.rca_value - 2.0).abs() < 1e-9, true);
+    }
+
+    #[rstest]
+    fn test_optimize_with_gc_constraints(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        let mut options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            gc_content: Some(GcContentConstraint::new(0.4, 0.7).unwrap()),
+            ..Default::default()
+        };
+
+        let result = optimize_for_single_organism("ARAR", &org_usage1, &options).unwrap();
+        assert_eq!(result.translated_seq, "ARAR");
+        assert_eq!(result.violations, vec![]);
+        assert_eq!(compute_gc_content(&result.seq) <= 0.7, true);
+
+        // alanine and arginine codons are at least 2/3 GC, so this can't be met and is reported
+        options.gc_content = None;
+        options.gc_window = Some(GcWindowConstraint::new(6, 0.2, 0.5).unwrap());
+        let result = optimize_for_single_organism("ARAR", &org_usage1, &options).unwrap();
+        assert_eq!(result.violations.is_empty(), false);
+        assert_eq!(
+            result
+                .violations
+                .iter()
+                .all(|violation| violation.constraint == "gc-window"),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_optimize_for_worst_host(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+        org_usage2: HashMap<char, HashMap<Codon, f64>>,
+    ) {
+        let usage_data: CodonUsageByResidueByOrganism =
+            HashMap::from([(1, org_usage1), (2, org_usage2)]);
+        let options = OptimizationOptions {
+            prohibited_preference_threshold: 0.0,
+            ..Default::default()
+        };
+
+        let result = optimize_for_worst_host("ARAR", &usage_data, &options).unwrap();
+        assert_eq!(result.translated_seq, "ARAR");
+
+        // GCA/CGA are the only codons that are at least 75% as good as the best codon
+        // in both organisms, anything else is worse for one of them
+        assert_eq!(result.seq, "GCACGAGCACGA");
+        assert_eq!(result.organism_scores.len(), 2);
+        let mean = (result.organism_scores[&1] + result.organism_scores[&2]) / 2.0;
+        assert_eq!((result.rca_value - mean).abs() < 1e-9, true);
+    }
+
+    #[rstest]
+    fn test_optimize_for_worst_host_missing_codon() {
+        // GCC is the best codon for the first host, but the second host doesn't use it at all
+        let usage_data: CodonUsageByResidueByOrganism = HashMap::from([
+            (
+                1,
+                HashMap::from([
+                    ('M', HashMap::from([(Codon::ATG, 1.0)])),
+                    ('A', HashMap::from([(Codon::GCT, 0.4), (Codon::GCC, 0.6)])),
+                ]),
+            ),
+            (
+                2,
+                HashMap::from([('A', HashMap::from([(Codon::GCT, 1.0)]))]),
+            ),
+        ]);
+        let options = OptimizationOptions {
+            prohibited_preference_threshold: 0.0,
+            ..Default::default()
+        };
+
+        let result = optimize_for_worst_host("MA", &usage_data, &options).unwrap();
+        assert_eq!(result.seq, "ATGGCT");
+    }
+
+    #[rstest]
+    fn test_harmonize_for_organism(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+        org_usage2: HashMap<char, HashMap<Codon, f64>>,
+    ) {
+        // GCG/CGG are the most used codons in organism 1, GCA/CGA in organism 2.
+        // GCT/CGT are the rarest in organism 1, GCG/CGG in organism 2.
+        let result = harmonize_for_organism(
+            "GCGCGGGCTCGT",
+            &org_usage1,
+            &org_usage2,
+            &OptimizationOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(result.seq, "GCACGAGCGCGG");
+        assert_eq!(result.translated_seq, "ARAR");
+
+        // protein input can't be harmonized
+        assert_eq!(
+            harmonize_for_organism(
+                "MKV",
+                &org_usage1,
+                &org_usage2,
+                &OptimizationOptions::default()
+            )
+            .is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_optimize_with_motifs(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        let options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            motifs: vec![
+                Motif::forbidden("lab primer", "GCGCGG").unwrap(),
+                Motif::required_at("tag", "GCTCGT", 0).unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let result = optimize_for_single_organism("ARAR", &org_usage1, &options).unwrap();
+        assert_eq!(result.violations, vec![]);
+        assert_eq!(result.seq.starts_with("GCTCGT"), true);
+        assert_eq!(result.seq.contains("GCGCGG"), false);
+        assert_eq!(result.seq.contains("CCGCGC"), false);
+        assert_eq!(
+            result
+                .motif_matches
+                .iter()
+                .map(|m| (m.motif.as_str(), m.position))
+                .collect::<Vec<_>>(),
+            vec![("tag", 0)]
+        );
+    }
+
+    #[rstest]
+    fn test_optimize_with_locked_region(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        // the locked GCTCGT would be followed by GCG, making the forbidden CGTGCG
+        // across the junction
+        let options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            locked_regions: vec![LockedRegion::Residues(0..2)],
+            motifs: vec![Motif::forbidden("junction", "CGTGCG").unwrap()],
+            ..Default::default()
+        };
+
+        let result = optimize_for_single_organism("GCTCGTGCTCGT", &org_usage1, &options).unwrap();
+        assert_eq!(result.seq.starts_with("GCTCGT"), true);
+        assert_eq!(result.seq.contains("CGTGCG"), false);
+        assert_eq!(result.violations, vec![]);
+        assert_eq!(
+            result.changes.iter().all(|change| change.position >= 6),
+            true
+        );
+
+        // a nucleotide range locks every codon it touches, and proteins can't be locked
+        let options = OptimizationOptions {
+            locked_regions: vec![LockedRegion::Nucleotides(2..4)],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_locked_codons("GCTCGTGCT", &options.locked_regions).unwrap(),
+            vec![Some(Codon::GCT), Some(Codon::CGT), None]
+        );
+        assert_eq!(
+            optimize_for_single_organism("ARAR", &org_usage1, &options).is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_with_host_defaults() {
+        let mut organism = Organism {
+            org_id: 1,
+            division: "refseq".to_string(),
+            assembly: "GCF_000005845.2".to_string(),
+            taxid: 511145,
+            species: "Escherichia coli".to_string(),
+            organelle: "genomic".to_string(),
+            translation_table: 11,
+            num_cds: 4_000,
+            num_codons: 1_300_000,
+            gc_perc: 51.7,
+            gc1_perc: 58.9,
+            gc2_perc: 40.8,
+            gc3_perc: 55.6,
+        };
+
+        let options = OptimizationOptions::default().with_host_defaults(&organism);
+        assert_eq!(
+            options.shine_dalgarno,
+            Some(ShineDalgarnoConstraint::default())
+        );
+
+        assert_eq!(options.splice_sites, None);
+
+        organism.translation_table = 1;
+        let options = OptimizationOptions::default().with_host_defaults(&organism);
+        assert_eq!(options.shine_dalgarno, None);
+        assert_eq!(options.splice_sites, Some(SpliceSiteConstraint::default()));
+        assert_eq!(
+            options.polyadenylation_signals,
+            Some(PolyadenylationSignalConstraint::default())
+        );
+
+        // plastids share table 11 with bacteria, but are neither prokaryotes nor nuclear
+        organism.translation_table = 11;
+        organism.organelle = "chloroplast".to_string();
+        let options = OptimizationOptions::default().with_host_defaults(&organism);
+        assert_eq!(options.shine_dalgarno, None);
+        assert_eq!(options.splice_sites, None);
+    }
+
+    #[rstest]
+    fn test_harmonize_with_low_complexity_constraint(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+        org_usage2: HashMap<char, HashMap<Codon, f64>>,
+    ) {
+        // harmonizing gives GCA four times in a row
+        let options = OptimizationOptions {
+            low_complexity: Some(LowComplexityConstraint::new(6, 3, 3)),
+            ..Default::default()
+        };
+        let result =
+            harmonize_for_organism("GCGGCGGCGGCG", &org_usage1, &org_usage2, &options).unwrap();
+
+        assert_eq!(result.translated_seq, "AAAA");
+        assert_eq!(result.seq.contains("GCAGCAGCAGCA"), false);
+        assert_eq!(result.violations, vec![]);
+    }
+
+    #[rstest]
+    fn test_optimize_for_multiple_organisms(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+        org_usage2: HashMap<char, HashMap<Codon, f64>>,
+        org_weights: SpeciesWeights,
+    ) {
+        let usage_data: CodonUsageByResidueByOrganism =
+            HashMap::from([(1, org_usage1), (2, org_usage2)]);
+        let options = OptimizationOptions {
+            prohibited_preference_threshold: 0.15,
+            host_rca_references: HashMap::from([(
+                1,
+                Codon::ALL.iter().map(|codon| (*codon, 2.0)).collect(),
+            )]),
+            ..Default::default()
+        };
+
+        let result =
+            optimize_for_multiple_organisms("ARRA", &usage_data, &org_weights, &options).unwrap();
+
+        assert_eq!(result.translated_seq, "ARRA");
+        assert_eq!(result.organism_scores.len(), 2);
+        // host 1 is scored against its reference, host 2 against its usage table
+        assert_eq!((result.organism_scores[&1] - 2.0).abs() < 1e-9, true);
+        let rca = compute_rca(
+            &result.seq,
+            &compute_rca_xyz_table_from_usage(&usage_data[&2]),
+        );
+        assert_eq!(
+            (result.organism_scores[&2] - rca.unwrap()).abs() < 1e-9,
+            true
+        );
+        // the headline rca is the weighted mean over the hosts, not the averaged table's
+        let mean = 0.33 * result.organism_scores[&1] + 0.67 * result.organism_scores[&2];
+        assert_eq!((result.rca_value - mean).abs() < 1e-9, true);
+
+        // these fall below the threshold in one of the organisms, so they should never be used
+        let prohibited = ["GCT", "CGT", "GCG", "CGG"];
+        for codon in result.seq.as_bytes().chunks(3) {
+            let codon = std::str::from_utf8(codon).unwrap();
+            assert_eq!(prohibited.contains(&codon), false);
+        }
+    }
+
+    #[rstest]
+    fn test_optimize_for_multiple_organisms_missing_weight(
+        org_usage1: HashMap<char, HashMap<Codon, f64>>,
+        org_usage2: HashMap<char, HashMap<Codon, f64>>,
+    ) {
+        let usage_data: CodonUsageByResidueByOrganism =
+            HashMap::from([(1, org_usage1), (2, org_usage2)]);
+        let weights: SpeciesWeights = HashMap::from([(1, 1.0)]);
+        let options = OptimizationOptions::default();
+
+        let result = optimize_for_multiple_organisms("ARRA", &usage_data, &weights, &options);
+
+        assert_eq!(result.is_err(), true);
+    }
 }
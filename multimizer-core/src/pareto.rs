@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::optimizations::{
+    optimize_for_multiple_organisms, CodonUsageByResidueByOrganism, OptimizationOptions,
+    OptimizationResult, SpeciesWeights,
+};
+use crate::utils::{compute_cai, compute_relative_adaptiveness, RelativeAdaptivenessTable};
+
+///
+/// A single point on the Pareto front -- the weights that produced it, the objective
+/// values (CAI against each organism, keyed by org_id) and the resulting sequence.
+/// RCA against each organism is available in `result.organism_scores`.
+///
+pub struct ParetoCandidate {
+    pub weights: SpeciesWeights,
+    pub scores: HashMap<i32, f64>,
+    pub result: OptimizationResult,
+}
+
+///
+/// Optimize a query sequence for multiple organisms and return the trade-off between them,
+/// instead of committing to a single set of species weights up front. This sequence can be
+/// either protein or DNA.
+///
+/// Every candidate is scored separately against each organism with the codon adaptation
+/// index (see `compute_cai`). The weight simplex is sampled (evenly for two organisms,
+/// otherwise each organism on its own, equal weights, and random weight vectors), a sequence
+/// is optimized for every weight vector, and only the non-dominated candidates are kept. A
+/// candidate dominates another if its score is at least as good for every organism and
+/// strictly better for at least one.
+///
+/// # Arguments
+/// - query seq
+/// - codon usage data for each organism, keyed by org_id
+/// - number of weight vectors to try
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - the non-dominated candidates
+///
+pub fn optimize_pareto_front(
+    query: &str,
+    usage_by_org: &CodonUsageByResidueByOrganism,
+    num_weightings: usize,
+    options: &OptimizationOptions,
+) -> Result<Vec<ParetoCandidate>> {
+    if usage_by_org.is_empty() {
+        anyhow::bail!("No codon usage data was provided for optimization!")
+    }
+
+    let mut org_ids: Vec<i32> = usage_by_org.keys().copied().collect();
+    org_ids.sort();
+
+    let weightings = sample_weightings(&org_ids, num_weightings.max(1), options.seed as u64);
+
+    let adaptiveness_by_org: HashMap<i32, RelativeAdaptivenessTable> = usage_by_org
+        .iter()
+        .map(|(org_id, usage)| (*org_id, compute_relative_adaptiveness(usage)))
+        .collect();
+
+    let mut candidates: Vec<ParetoCandidate> = Vec::with_capacity(weightings.len());
+    for weights in weightings {
+        let result = optimize_for_multiple_organisms(query, usage_by_org, &weights, options)?;
+        if candidates.iter().any(|c| c.result.seq == result.seq) {
+            continue;
+        }
+
+        let mut scores = HashMap::new();
+        for (org_id, adaptiveness) in &adaptiveness_by_org {
+            scores.insert(*org_id, compute_cai(&result.seq, adaptiveness)?);
+        }
+
+        candidates.push(ParetoCandidate {
+            weights,
+            scores,
+            result,
+        });
+    }
+
+    let front: Vec<bool> = candidates
+        .iter()
+        .map(|candidate| {
+            !candidates
+                .iter()
+                .any(|other| dominates(&other.scores, &candidate.scores, &org_ids))
+        })
+        .collect();
+
+    Ok(candidates
+        .into_iter()
+        .zip(front)
+        .filter(|(_, on_front)| *on_front)
+        .map(|(candidate, _)| candidate)
+        .collect())
+}
+
+/// Does `a` dominate `b`?
+fn dominates(a: &HashMap<i32, f64>, b: &HashMap<i32, f64>, org_ids: &[i32]) -> bool {
+    let mut strictly_better = false;
+    for org_id in org_ids {
+        let score_a = a.get(org_id).copied().unwrap_or(f64::MIN);
+        let score_b = b.get(org_id).copied().unwrap_or(f64::MIN);
+        if score_a < score_b {
+            return false;
+        }
+        if score_a > score_b {
+            strictly_better = true;
+        }
+    }
+    strictly_better
+}
+
+/// Sample weight vectors from the simplex
+fn sample_weightings(org_ids: &[i32], num_weightings: usize, seed: u64) -> Vec<SpeciesWeights> {
+    let n = org_ids.len();
+    let to_weights =
+        |values: Vec<f64>| -> SpeciesWeights { org_ids.iter().copied().zip(values).collect() };
+
+    if n == 1 {
+        return vec![to_weights(vec![1.0])];
+    }
+
+    if n == 2 {
+        let steps = num_weightings.max(2);
+        return (0..steps)
+            .map(|i| {
+                let w = i as f64 / (steps - 1) as f64;
+                to_weights(vec![w, 1.0 - w])
+            })
+            .collect();
+    }
+
+    let mut weightings: Vec<SpeciesWeights> = Vec::with_capacity(num_weightings.max(n + 1));
+
+    // every organism on its own, then all of them equally
+    for i in 0..n {
+        let mut values = vec![0.0; n];
+        values[i] = 1.0;
+        weightings.push(to_weights(values));
+    }
+    weightings.push(to_weights(vec![1.0 / n as f64; n]));
+
+    // fill up the rest uniformly at random from the simplex
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    while weightings.len() < num_weightings {
+        let values: Vec<f64> = (0..n).map(|_| -(1.0 - rng.gen::<f64>()).ln()).collect();
+        let total: f64 = values.iter().sum();
+        weightings.push(to_weights(values.into_iter().map(|v| v / total).collect()));
+    }
+
+    weightings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    use crate::models::Codon;
+    use crate::strategies::MostFrequentCodonStrategy;
+
+    #[fixture]
+    fn usage_by_org() -> CodonUsageByResidueByOrganism {
+        // organism 1 prefers AGA for arginine, organism 2 prefers CGC
+        HashMap::from([
+            (
+                1,
+                HashMap::from([
+                    ('M', HashMap::from([(Codon::ATG, 1.0)])),
+                    (
+                        'R',
+                        HashMap::from([
+                            (Codon::CGT, 0.1),
+                            (Codon::CGC, 0.1),
+                            (Codon::CGA, 0.1),
+                            (Codon::CGG, 0.1),
+                            (Codon::AGA, 0.5),
+                            (Codon::AGG, 0.1),
+                        ]),
+                    ),
+                ]),
+            ),
+            (
+                2,
+                HashMap::from([
+                    ('M', HashMap::from([(Codon::ATG, 1.0)])),
+                    (
+                        'R',
+                        HashMap::from([
+                            (Codon::CGT, 0.1),
+                            (Codon::CGC, 0.5),
+                            (Codon::CGA, 0.1),
+                            (Codon::CGG, 0.1),
+                            (Codon::AGA, 0.1),
+                            (Codon::AGG, 0.1),
+                        ]),
+                    ),
+                ]),
+            ),
+        ])
+    }
+
+    #[rstest]
+    fn test_optimize_pareto_front(usage_by_org: CodonUsageByResidueByOrganism) {
+        let options = OptimizationOptions {
+            prohibited_preference_threshold: 0.0,
+            strategy: Box::new(MostFrequentCodonStrategy),
+            ..Default::default()
+        };
+
+        let front = optimize_pareto_front("MRRR", &usage_by_org, 5, &options).unwrap();
+
+        // the two organisms disagree, so there is more than one point on the front
+        assert_eq!(front.len() > 1, true);
+        for candidate in &front {
+            assert_eq!(candidate.scores.len(), 2);
+            assert_eq!(
+                (candidate.weights.values().sum::<f64>() - 1.0).abs() < 1e-9,
+                true
+            );
+            for other in &front {
+                let org_ids = [1, 2];
+                assert_eq!(dominates(&other.scores, &candidate.scores, &org_ids), false);
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_sample_weightings() {
+        let weightings = sample_weightings(&[1, 2, 3], 10, 42);
+        assert_eq!(weightings.len(), 10);
+        for weights in weightings {
+            assert_eq!((weights.values().sum::<f64>() - 1.0).abs() < 1e-9, true);
+        }
+    }
+}
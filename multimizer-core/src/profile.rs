@@ -0,0 +1,231 @@
+use anyhow::Result;
+
+use crate::consts::CodonToAA;
+use crate::models::Codon;
+use crate::optimizations::CodonUsageByResidue;
+use crate::utils::{
+    compute_cai, compute_gc_content, compute_rca, compute_rca_xyz_table_from_usage,
+    compute_relative_adaptiveness, normalize_codon_usage, split_into_codons,
+};
+
+///
+/// Window and step of an adaptation profile, both in codons. The default window of 18
+/// codons is the one used for %MinMax by Clarke and Clark (2008).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileOptions {
+    pub window_size: usize,
+    pub step: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        ProfileOptions {
+            window_size: 18,
+            step: 1,
+        }
+    }
+}
+
+///
+/// Scores of a single window of the sequence. Positions are 0-based, end-exclusive
+/// nucleotide positions.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileWindow {
+    pub start: usize,
+    pub end: usize,
+    /// Codon adaptation index of the window (see `compute_cai`)
+    pub cai: f64,
+    /// Relative codon adaptation of the window (see `compute_rca`)
+    pub rca: f64,
+    /// %MinMax, from -100 (only the rarest codons) to 100 (only the most used codons)
+    pub min_max: f64,
+    /// GC content, as a fraction between 0 and 1
+    pub gc: f64,
+}
+
+///
+/// Sliding-window scores along a DNA sequence, in order of position
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptationProfile {
+    pub window_size: usize,
+    pub step: usize,
+    pub windows: Vec<ProfileWindow>,
+}
+
+///
+/// Compute the %MinMax of a run of codons (Clarke and Clark 2008) -- how the usage of the
+/// codons compares to always using the most used (100) or the rarest (-100) synonymous
+/// codons, with 0 being the average usage of the synonymous codons.
+///
+/// Stop codons and codons of residues missing from the usage data are skipped.
+///
+/// # Arguments
+/// - the codons
+/// - codon usage data, normalized per residue
+///
+/// # Returns
+/// - the %MinMax
+///
+fn compute_min_max(codons: &[Codon], normalized_usage: &CodonUsageByResidue) -> f64 {
+    let codon_to_aa = CodonToAA::new();
+    let (mut actual, mut max, mut min, mut average) = (0.0, 0.0, 0.0, 0.0);
+
+    for codon in codons {
+        let Some(aa) = codon_to_aa.convert(codon).filter(|aa| *aa != '*') else {
+            continue;
+        };
+        let Some(preferences) = normalized_usage.get(&aa).filter(|p| !p.is_empty()) else {
+            continue;
+        };
+        actual += preferences.get(codon).copied().unwrap_or(0.0);
+        max += preferences.values().copied().fold(f64::MIN, f64::max);
+        min += preferences.values().copied().fold(f64::MAX, f64::min);
+        average += preferences.values().sum::<f64>() / preferences.len() as f64;
+    }
+
+    if actual >= average {
+        if max > average {
+            100.0 * (actual - average) / (max - average)
+        } else {
+            0.0
+        }
+    } else if average > min {
+        -100.0 * (average - actual) / (average - min)
+    } else {
+        0.0
+    }
+}
+
+///
+/// Compute CAI, RCA, %MinMax and GC content in sliding windows along a DNA sequence, to
+/// find clusters of rare codons that a single score for the whole sequence hides. Works
+/// for input sequences and optimized ones alike (see `OptimizationResult::adaptation_profile`).
+///
+/// A sequence shorter than the window is scored as a single window.
+///
+/// # Arguments
+/// - dna sequence, in frame
+/// - codon usage data of the host
+/// - window and step
+///
+/// # Returns
+/// - the profile
+///
+pub fn compute_adaptation_profile(
+    dna: &str,
+    codon_usage: &CodonUsageByResidue,
+    options: &ProfileOptions,
+) -> Result<AdaptationProfile> {
+    if options.window_size == 0 || options.step == 0 {
+        anyhow::bail!("The window size and step of a profile must be at least one codon")
+    }
+
+    let dna = dna.to_uppercase();
+    let codons = split_into_codons(&dna)?;
+    let weights = compute_relative_adaptiveness(codon_usage);
+    let rca_xyz_table = compute_rca_xyz_table_from_usage(codon_usage);
+    let normalized_usage = normalize_codon_usage(codon_usage);
+
+    let window_size = options.window_size.min(codons.len());
+    let mut windows = vec![];
+    let mut start = 0;
+    while start + window_size <= codons.len() && !codons.is_empty() {
+        let window = &dna[start * 3..(start + window_size) * 3];
+        windows.push(ProfileWindow {
+            start: start * 3,
+            end: (start + window_size) * 3,
+            cai: compute_cai(window, &weights)?,
+            rca: compute_rca(window, &rca_xyz_table)?,
+            min_max: compute_min_max(&codons[start..start + window_size], &normalized_usage),
+            gc: compute_gc_content(window),
+        });
+        start += options.step;
+    }
+
+    Ok(AdaptationProfile {
+        window_size: options.window_size,
+        step: options.step,
+        windows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    const EPSILON: f64 = 1e-9;
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.1),
+                    (Codon::GCC, 0.2),
+                    (Codon::GCA, 0.3),
+                    (Codon::GCG, 0.4),
+                ]),
+            ),
+            ('K', HashMap::from([(Codon::AAA, 0.7), (Codon::AAG, 0.3)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_adaptation_profile(usage: CodonUsageByResidue) {
+        // the most used codons, then the rarest ones
+        let dna = "GCGAAAGCGGCTAAGGCT";
+        let options = ProfileOptions {
+            window_size: 3,
+            step: 3,
+        };
+        let profile = compute_adaptation_profile(dna, &usage, &options).unwrap();
+
+        assert_eq!(profile.windows.len(), 2);
+        let (first, second) = (&profile.windows[0], &profile.windows[1]);
+        assert_eq!((first.start, first.end), (0, 9));
+        assert_eq!((second.start, second.end), (9, 18));
+
+        assert_eq!((first.min_max - 100.0).abs() < EPSILON, true);
+        assert_eq!((second.min_max + 100.0).abs() < EPSILON, true);
+        assert_eq!((first.cai - 1.0).abs() < EPSILON, true);
+        assert_eq!(second.cai < first.cai, true);
+        assert_eq!(first.rca > 0.0 && second.rca > 0.0, true);
+        assert_eq!((first.gc - 6.0 / 9.0).abs() < EPSILON, true);
+
+        // overlapping windows
+        let options = ProfileOptions {
+            window_size: 2,
+            step: 1,
+        };
+        let profile = compute_adaptation_profile(dna, &usage, &options).unwrap();
+        assert_eq!(profile.windows.len(), 5);
+
+        // a codon missing from the usage data, next to synonyms of equal usage
+        let equal_usage =
+            HashMap::from([('G', HashMap::from([(Codon::GGT, 0.5), (Codon::GGC, 0.5)]))]);
+        let profile = compute_adaptation_profile("GGA", &equal_usage, &ProfileOptions::default());
+        assert_eq!(profile.unwrap().windows[0].min_max, 0.0);
+
+        // shorter than the window
+        let profile = compute_adaptation_profile("GCG", &usage, &ProfileOptions::default());
+        assert_eq!(profile.unwrap().windows.len(), 1);
+
+        let options = ProfileOptions {
+            window_size: 0,
+            step: 1,
+        };
+        assert_eq!(
+            compute_adaptation_profile(dna, &usage, &options).is_err(),
+            true
+        );
+    }
+}
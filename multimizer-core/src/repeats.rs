@@ -0,0 +1,309 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::constraints::{ChangeScorer, SequenceConstraint, Violation};
+use crate::utils::{complement, reverse_complement_bytes};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RepeatKind {
+    /// The same sequence occurs twice on the same strand
+    Direct,
+    /// The second copy is the reverse complement of the first (e.g. a hairpin stem)
+    Inverted,
+}
+
+///
+/// A pair of repeated segments in a DNA sequence. Both copies are `length` nucleotides long
+/// and start at `first` and `second` (0-based, forward strand, `first < second`).
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Repeat {
+    pub kind: RepeatKind,
+    pub first: usize,
+    pub second: usize,
+    pub length: usize,
+}
+
+///
+/// Find all maximal direct and inverted repeats of at least `min_length` nucleotides.
+///
+/// Direct repeats may overlap (as in tandem repeats, which are reported once per offset
+/// between the copies). The two copies of an inverted repeat
+/// never overlap, so palindromic sites like GAATTC are not reported on their own.
+///
+/// # Arguments
+/// - dna sequence
+/// - minimum length of a repeat
+///
+/// # Returns
+/// - the repeats, ordered by position of the first copy
+///
+pub fn find_repeats(dna: &str, min_length: usize) -> Vec<Repeat> {
+    let bases = dna.to_uppercase().into_bytes();
+    let n = bases.len();
+    if min_length == 0 || n < min_length {
+        return vec![];
+    }
+
+    // positions of every k-mer (k = min_length) -- every repeat starts with a shared seed
+    let mut kmer_positions: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (position, kmer) in bases.windows(min_length).enumerate() {
+        kmer_positions.entry(kmer).or_default().push(position);
+    }
+
+    // every seed pair lies on the diagonal of exactly one maximal repeat, which is only
+    // extended from its first seed
+    let mut repeats: Vec<Repeat> = vec![];
+
+    // direct repeats
+    for positions in kmer_positions.values() {
+        for (a, &i) in positions.iter().enumerate() {
+            for &j in &positions[a + 1..] {
+                if i > 0 && bases[i - 1] == bases[j - 1] {
+                    continue;
+                }
+                let mut length = min_length;
+                while j + length < n && bases[i + length] == bases[j + length] {
+                    length += 1;
+                }
+                repeats.push(Repeat {
+                    kind: RepeatKind::Direct,
+                    first: i,
+                    second: j,
+                    length,
+                });
+            }
+        }
+    }
+
+    // inverted repeats: a k-mer at i whose reverse complement occurs at j
+    for (kmer, positions) in &kmer_positions {
+        let Some(reverse_positions) = kmer_positions.get(reverse_complement_bytes(kmer).as_slice())
+        else {
+            continue;
+        };
+        for &i in positions {
+            for &j in reverse_positions {
+                if i + min_length > j {
+                    continue;
+                }
+                // the seed further out, at (i - 1, j + 1), starts the same repeat
+                if i > 0 && j + min_length < n && bases[j + min_length] == complement(bases[i - 1])
+                {
+                    continue;
+                }
+
+                // growing the first copy towards the loop grows the second one too
+                let (mut j, mut length) = (j, min_length);
+                while i + length < j - 1 && bases[j - 1] == complement(bases[i + length]) {
+                    j -= 1;
+                    length += 1;
+                }
+                repeats.push(Repeat {
+                    kind: RepeatKind::Inverted,
+                    first: i,
+                    second: j,
+                    length,
+                });
+            }
+        }
+    }
+
+    repeats.sort_by_key(|r| (r.first, r.second, r.length, r.kind == RepeatKind::Inverted));
+    repeats
+}
+
+///
+/// Constraint that forbids direct and inverted repeats longer than `max_length` nucleotides.
+/// A violation spans the second copy of the repeat. Its penalty, the number of nucleotides
+/// over `max_length`, is also the number of pairs of repeated k-mers (k = `max_length + 1`)
+/// in the repeat, which is what the repair step counts around a change to score it.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatConstraint {
+    pub max_length: usize,
+}
+
+impl SequenceConstraint for RepeatConstraint {
+    fn name(&self) -> &str {
+        "repeat"
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        find_repeats(dna, self.max_length + 1)
+            .into_iter()
+            .map(|repeat| Violation {
+                constraint: self.name().to_string(),
+                start: repeat.second,
+                end: repeat.second + repeat.length,
+                penalty: (repeat.length - self.max_length) as f64,
+                description: format!(
+                    "{:?} repeat of {} bp at positions {} and {}",
+                    repeat.kind, repeat.length, repeat.first, repeat.second
+                ),
+            })
+            .collect()
+    }
+
+    fn change_scorer<'a>(&'a self, dna: &'a str) -> ChangeScorer<'a> {
+        let k = self.max_length + 1;
+        let bases = dna.as_bytes();
+        let mut kmer_positions: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for (position, kmer) in bases.windows(k).enumerate() {
+            kmer_positions.entry(kmer).or_default().push(position);
+        }
+
+        // pairs of occurrences of a k-mer (direct) and with its reverse complement (inverted,
+        // the copies may not overlap), over the k-mers a change touches
+        let pairs = move |positions: &HashMap<Vec<u8>, Vec<usize>>| -> usize {
+            positions
+                .iter()
+                .map(|(kmer, at)| {
+                    let direct = at.len() * at.len().saturating_sub(1) / 2;
+                    let reverse = reverse_complement_bytes(kmer);
+                    let inverted = match reverse.cmp(kmer) {
+                        Ordering::Less => 0,
+                        Ordering::Equal => at
+                            .iter()
+                            .enumerate()
+                            .map(|(a, i)| at[a + 1..].iter().filter(|j| *j - i >= k).count())
+                            .sum(),
+                        Ordering::Greater => at
+                            .iter()
+                            .map(|i| {
+                                positions[&reverse]
+                                    .iter()
+                                    .filter(|j| i.abs_diff(**j) >= k)
+                                    .count()
+                            })
+                            .sum(),
+                    };
+                    direct + inverted
+                })
+                .sum()
+        };
+
+        Box::new(move |start, replacement| {
+            let end = start + replacement.len();
+            let from = start.saturating_sub(k - 1);
+            let to = (end + k - 1).min(bases.len());
+            let changed = [&bases[from..start], replacement.as_bytes(), &bases[end..to]].concat();
+
+            // positions before and after the change of every k-mer the change touches, and of
+            // their reverse complements
+            let mut before: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+            for kmer in bases[from..to].windows(k).chain(changed.windows(k)) {
+                for key in [kmer.to_vec(), reverse_complement_bytes(kmer)] {
+                    let at = kmer_positions
+                        .get(key.as_slice())
+                        .cloned()
+                        .unwrap_or_default();
+                    before.entry(key).or_insert(at);
+                }
+            }
+            let mut after = before.clone();
+            for at in after.values_mut() {
+                at.retain(|position| !(from..to.saturating_sub(k - 1)).contains(position));
+            }
+            for (offset, kmer) in changed.windows(k).enumerate() {
+                after.get_mut(kmer).unwrap().push(from + offset);
+            }
+
+            pairs(&after) as f64 - pairs(&before) as f64
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use crate::constraints::enforce_constraints;
+    use crate::models::Codon;
+    use crate::optimizations::CodonUsageByResidue;
+    use crate::utils::split_into_codons;
+
+    #[rstest]
+    fn test_find_repeats() {
+        // ACGATCCT at 2 is repeated directly at 12, and inverted (AGGATCGT) at 22
+        let dna = "GAACGATCCTTCACGATCCTCCAGGATCGTAA";
+        let repeat = |kind, first, second| Repeat {
+            kind,
+            first,
+            second,
+            length: 8,
+        };
+
+        assert_eq!(
+            find_repeats(dna, 8),
+            vec![
+                repeat(RepeatKind::Direct, 2, 12),
+                repeat(RepeatKind::Inverted, 2, 22),
+                repeat(RepeatKind::Inverted, 12, 22),
+            ]
+        );
+        assert_eq!(find_repeats(dna, 9), vec![]);
+
+        // nothing repeats at this length
+        assert_eq!(find_repeats("ACGTTGCA", 5), vec![]);
+
+        // a tandem repeat is reported once for every offset between its copies
+        let tandem: Vec<(usize, usize, usize)> = find_repeats("ACACACACAC", 4)
+            .iter()
+            .map(|r| (r.first, r.second, r.length))
+            .collect();
+        assert_eq!(tandem, vec![(0, 2, 8), (0, 4, 6), (0, 6, 4)]);
+    }
+
+    #[rstest]
+    fn test_remove_repeats() {
+        let usage: CodonUsageByResidue = HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.4),
+                    (Codon::GCC, 0.3),
+                    (Codon::GCA, 0.2),
+                    (Codon::GCG, 0.1),
+                ]),
+            ),
+            ('K', HashMap::from([(Codon::AAA, 0.7), (Codon::AAG, 0.3)])),
+        ]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> =
+            vec![Box::new(RepeatConstraint { max_length: 8 })];
+
+        let mut codons = split_into_codons("GCTAAAGCTAAAGCTAAAGCTAAA").unwrap();
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(remaining, vec![]);
+        assert_eq!(find_repeats(&dna, 9), vec![]);
+    }
+
+    #[rstest]
+    fn test_repeat_change_scorer() {
+        let constraint = RepeatConstraint { max_length: 7 };
+        let dna = "GAACGATCCTTCACGATCCTCCAGGATCGTAA";
+        let penalty = |seq: &str| -> f64 {
+            constraint
+                .find_violations(seq)
+                .iter()
+                .map(|violation| violation.penalty)
+                .sum()
+        };
+
+        // every single base substitution is scored like a full rescan
+        let scorer = constraint.change_scorer(dna);
+        for position in 0..dna.len() {
+            for base in ["A", "C", "G", "T"] {
+                let changed = format!("{}{base}{}", &dna[..position], &dna[position + 1..]);
+                assert_eq!(scorer(position, base), penalty(&changed) - penalty(dna));
+            }
+        }
+    }
+}
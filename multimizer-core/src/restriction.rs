@@ -0,0 +1,316 @@
+use anyhow::Result;
+
+use crate::constraints::{SequenceConstraint, Violation};
+use crate::utils::{find_iupac_matches, reverse_complement, validate_iupac_pattern};
+
+///
+/// Catalog of commonly used restriction enzymes and their recognition sequences (5' -> 3'),
+/// written with IUPAC nucleotide codes.
+///
+pub const RESTRICTION_ENZYME_CATALOG: &[(&str, &str)] = &[
+    // type II, palindromic
+    ("AatII", "GACGTC"),
+    ("AccI", "GTMKAC"),
+    ("AflII", "CTTAAG"),
+    ("AgeI", "ACCGGT"),
+    ("ApaI", "GGGCCC"),
+    ("ApaLI", "GTGCAC"),
+    ("AscI", "GGCGCGCC"),
+    ("AvaI", "CYCGRG"),
+    ("AvrII", "CCTAGG"),
+    ("BamHI", "GGATCC"),
+    ("BanII", "GRGCYC"),
+    ("BglII", "AGATCT"),
+    ("BsiWI", "CGTACG"),
+    ("BsrGI", "TGTACA"),
+    ("BstXI", "CCANNNNNNTGG"),
+    ("ClaI", "ATCGAT"),
+    ("DraI", "TTTAAA"),
+    ("EagI", "CGGCCG"),
+    ("EcoRI", "GAATTC"),
+    ("EcoRV", "GATATC"),
+    ("FseI", "GGCCGGCC"),
+    ("HincII", "GTYRAC"),
+    ("HindIII", "AAGCTT"),
+    ("HpaI", "GTTAAC"),
+    ("KpnI", "GGTACC"),
+    ("MfeI", "CAATTG"),
+    ("MluI", "ACGCGT"),
+    ("NcoI", "CCATGG"),
+    ("NdeI", "CATATG"),
+    ("NheI", "GCTAGC"),
+    ("NotI", "GCGGCCGC"),
+    ("NsiI", "ATGCAT"),
+    ("PacI", "TTAATTAA"),
+    ("PmeI", "GTTTAAAC"),
+    ("PstI", "CTGCAG"),
+    ("PvuI", "CGATCG"),
+    ("PvuII", "CAGCTG"),
+    ("SacI", "GAGCTC"),
+    ("SacII", "CCGCGG"),
+    ("SalI", "GTCGAC"),
+    ("SbfI", "CCTGCAGG"),
+    ("ScaI", "AGTACT"),
+    ("SfiI", "GGCCNNNNNGGCC"),
+    ("SmaI", "CCCGGG"),
+    ("SpeI", "ACTAGT"),
+    ("SphI", "GCATGC"),
+    ("SwaI", "ATTTAAAT"),
+    ("XbaI", "TCTAGA"),
+    ("XhoI", "CTCGAG"),
+    ("XmaI", "CCCGGG"),
+    // type IIS, non-palindromic -- these are the Golden Gate workhorses
+    ("AarI", "CACCTGC"),
+    ("BbsI", "GAAGAC"),
+    ("BsaI", "GGTCTC"),
+    ("BsmBI", "CGTCTC"),
+    ("BsmI", "GAATGC"),
+    ("BspMI", "ACCTGC"),
+    ("BtgZI", "GCGATG"),
+    ("Esp3I", "CGTCTC"),
+    ("PaqCI", "CACCTGC"),
+    ("SapI", "GCTCTTC"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+///
+/// A restriction enzyme and its recognition sequence
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestrictionEnzyme {
+    pub name: String,
+    pub site: String,
+}
+
+///
+/// An occurrence of a recognition sequence in a DNA sequence. The position is always
+/// on the forward strand, even for matches on the reverse strand.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestrictionSite {
+    pub enzyme: String,
+    pub position: usize,
+    pub strand: Strand,
+}
+
+impl RestrictionEnzyme {
+    ///
+    /// Create a new (custom) restriction enzyme
+    ///
+    /// # Arguments
+    /// - name of the enzyme
+    /// - recognition sequence, 5' -> 3', with IUPAC nucleotide codes
+    ///
+    /// # Returns
+    /// - the enzyme
+    ///
+    pub fn new(name: &str, site: &str) -> Result<RestrictionEnzyme> {
+        Ok(RestrictionEnzyme {
+            name: name.to_string(),
+            site: validate_iupac_pattern(site)?,
+        })
+    }
+
+    ///
+    /// Look up an enzyme in the bundled catalog. The lookup is case insensitive.
+    ///
+    /// # Arguments
+    /// - name of the enzyme, e.g. `BsaI`
+    ///
+    /// # Returns
+    /// - the enzyme
+    ///
+    pub fn from_catalog(name: &str) -> Result<RestrictionEnzyme> {
+        match RESTRICTION_ENZYME_CATALOG
+            .iter()
+            .find(|(enzyme, _)| enzyme.eq_ignore_ascii_case(name))
+        {
+            Some((enzyme, site)) => RestrictionEnzyme::new(enzyme, site),
+            None => anyhow::bail!("Unknown restriction enzyme: {name}"),
+        }
+    }
+
+    ///
+    /// Get every enzyme in the bundled catalog
+    ///
+    pub fn catalog() -> Vec<RestrictionEnzyme> {
+        RESTRICTION_ENZYME_CATALOG
+            .iter()
+            .map(|(name, site)| RestrictionEnzyme {
+                name: name.to_string(),
+                site: site.to_string(),
+            })
+            .collect()
+    }
+
+    ///
+    /// Whether the recognition sequence reads the same on both strands
+    ///
+    pub fn is_palindromic(&self) -> bool {
+        reverse_complement(&self.site) == self.site
+    }
+
+    ///
+    /// Find every recognition site of the enzyme in a DNA sequence, on both strands
+    ///
+    /// # Arguments
+    /// - dna sequence
+    ///
+    /// # Returns
+    /// - the sites, forward strand first
+    ///
+    pub fn find_sites(&self, dna: &str) -> Vec<RestrictionSite> {
+        let mut sites: Vec<RestrictionSite> = find_iupac_matches(dna, &self.site)
+            .into_iter()
+            .map(|position| RestrictionSite {
+                enzyme: self.name.clone(),
+                position,
+                strand: Strand::Forward,
+            })
+            .collect();
+
+        // palindromic sites would just be found again
+        if !self.is_palindromic() {
+            let reverse_site = reverse_complement(&self.site);
+            sites.extend(
+                find_iupac_matches(dna, &reverse_site)
+                    .into_iter()
+                    .map(|position| RestrictionSite {
+                        enzyme: self.name.clone(),
+                        position,
+                        strand: Strand::Reverse,
+                    }),
+            );
+        }
+
+        sites
+    }
+}
+
+///
+/// Constraint that forbids the recognition sites of a set of restriction enzymes
+///
+pub struct RestrictionSiteConstraint {
+    pub enzymes: Vec<RestrictionEnzyme>,
+}
+
+impl RestrictionSiteConstraint {
+    ///
+    /// Build the constraint from enzyme names in the bundled catalog
+    ///
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<RestrictionSiteConstraint> {
+        let enzymes = names
+            .iter()
+            .map(|name| RestrictionEnzyme::from_catalog(name.as_ref()))
+            .collect::<Result<Vec<RestrictionEnzyme>>>()?;
+
+        Ok(RestrictionSiteConstraint { enzymes })
+    }
+}
+
+impl SequenceConstraint for RestrictionSiteConstraint {
+    fn name(&self) -> &str {
+        "restriction-site"
+    }
+
+    fn is_hard(&self) -> bool {
+        true
+    }
+
+    fn context(&self) -> Option<usize> {
+        self.enzymes.iter().map(|enzyme| enzyme.site.len()).max()
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        self.enzymes
+            .iter()
+            .flat_map(|enzyme| {
+                enzyme.find_sites(dna).into_iter().map(|site| Violation {
+                    constraint: self.name().to_string(),
+                    start: site.position,
+                    end: site.position + enzyme.site.len(),
+                    penalty: 1.0,
+                    description: format!(
+                        "{} site ({:?} strand) at position {}",
+                        enzyme.name, site.strand, site.position
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use crate::constraints::enforce_constraints;
+    use crate::models::Codon;
+    use crate::optimizations::CodonUsageByResidue;
+    use crate::utils::split_into_codons;
+
+    #[rstest]
+    fn test_find_sites_on_both_strands() {
+        let bsai = RestrictionEnzyme::from_catalog("bsai").unwrap();
+        assert_eq!(bsai.is_palindromic(), false);
+
+        // forward site at 2, reverse complement (GAGACC) at 12
+        let sites = bsai.find_sites("AAGGTCTCAAAAGAGACCAA");
+        assert_eq!(
+            sites,
+            vec![
+                RestrictionSite {
+                    enzyme: "BsaI".to_string(),
+                    position: 2,
+                    strand: Strand::Forward
+                },
+                RestrictionSite {
+                    enzyme: "BsaI".to_string(),
+                    position: 12,
+                    strand: Strand::Reverse
+                },
+            ]
+        );
+
+        // palindromic sites are only reported once
+        let ecori = RestrictionEnzyme::from_catalog("EcoRI").unwrap();
+        assert_eq!(ecori.find_sites("GAATTC").len(), 1);
+
+        // degenerate sites
+        let sfii = RestrictionEnzyme::from_catalog("SfiI").unwrap();
+        assert_eq!(sfii.find_sites("GGCCAGTCAGGCC").len(), 1);
+
+        assert_eq!(
+            RestrictionEnzyme::from_catalog("NotAnEnzyme").is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_remove_sites() {
+        let usage: CodonUsageByResidue = HashMap::from([
+            ('E', HashMap::from([(Codon::GAA, 0.7), (Codon::GAG, 0.3)])),
+            ('F', HashMap::from([(Codon::TTT, 0.6), (Codon::TTC, 0.4)])),
+        ]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![Box::new(
+            RestrictionSiteConstraint::from_names(&["EcoRI"]).unwrap(),
+        )];
+
+        let mut codons = split_into_codons("GAATTCGAATTC").unwrap();
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+
+        assert_eq!(remaining, vec![]);
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+        assert_eq!(dna, "GAATTTGAATTT");
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::models::Codon;
+use crate::optimizations::CodonUsageByResidue;
+
+///
+/// Walker/Vose alias table for sampling codons in constant time.
+///
+/// The table is built once from the preferences of a single residue and
+/// can then be sampled as many times as needed.
+///
+#[derive(Debug, Clone)]
+pub struct AliasTable {
+    codons: Vec<Codon>,
+    prob: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    ///
+    /// Build an alias table from a set of codon preferences
+    ///
+    /// # Arguments
+    /// - preferences: codon -> weight, weights do not need to be normalized
+    ///
+    /// # Returns
+    /// - the alias table
+    ///
+    pub fn new(preferences: &HashMap<Codon, f64>) -> Result<AliasTable> {
+        // sort so the table (and therefore the samples) don't depend on HashMap ordering
+        let mut entries: Vec<(Codon, f64)> = preferences.iter().map(|(c, w)| (*c, *w)).collect();
+        entries.sort_by_key(|(codon, _)| *codon);
+
+        if entries.iter().any(|(_, w)| !w.is_finite() || *w < 0.0) {
+            anyhow::bail!("Codon preferences must be finite and non-negative")
+        }
+
+        let total: f64 = entries.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            anyhow::bail!("At least one codon must have a positive preference")
+        }
+
+        let n = entries.len();
+        let codons: Vec<Codon> = entries.iter().map(|(c, _)| *c).collect();
+        let mut scaled: Vec<f64> = entries.iter().map(|(_, w)| w * n as f64 / total).collect();
+        let mut prob = vec![0.0; n];
+        let mut alias = vec![0; n];
+
+        let mut small: Vec<usize> = Vec::new();
+        let mut large: Vec<usize> = Vec::new();
+        for (i, p) in scaled.iter().enumerate() {
+            if *p < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+            prob[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] = (scaled[l] + scaled[s]) - 1.0;
+            if scaled[l] < 1.0 {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+
+        // anything left over is (up to rounding error) exactly 1.0
+        for i in large.into_iter().chain(small) {
+            prob[i] = 1.0;
+        }
+
+        Ok(AliasTable {
+            codons,
+            prob,
+            alias,
+        })
+    }
+
+    ///
+    /// Draw a single codon from the table
+    ///
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Codon {
+        let i = rng.gen_range(0..self.codons.len());
+        if rng.gen::<f64>() < self.prob[i] {
+            self.codons[i]
+        } else {
+            self.codons[self.alias[i]]
+        }
+    }
+}
+
+///
+/// Seeded codon sampler that owns a single RNG stream for a whole optimization.
+///
+/// The per-residue distributions are precomputed as alias tables when the
+/// sampler is created, so sampling a codon is constant time. Samplers for
+/// other sequences in a batch can be derived with `derive_stream`; they
+/// share the tables but draw from an independent stream of the same seed.
+///
+#[derive(Debug, Clone)]
+pub struct CodonSampler {
+    tables: Arc<HashMap<char, AliasTable>>,
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl CodonSampler {
+    ///
+    /// Create a new sampler for a codon usage table
+    ///
+    /// # Arguments
+    /// - usage: codon usage data for the organism
+    /// - seed: seed for the RNG stream
+    ///
+    /// # Returns
+    /// - the sampler
+    ///
+    pub fn new(usage: &CodonUsageByResidue, seed: u64) -> Result<CodonSampler> {
+        let mut tables = HashMap::new();
+        for (aa, preferences) in usage {
+            let table = AliasTable::new(preferences)
+                .map_err(|e| anyhow::anyhow!("Invalid usage data for residue {aa}: {e}"))?;
+            tables.insert(*aa, table);
+        }
+
+        Ok(CodonSampler {
+            tables: Arc::new(tables),
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        })
+    }
+
+    ///
+    /// Derive a sampler for another sequence in a batch. The new sampler shares
+    /// the precomputed tables and seed, but draws from its own stream. Stream ids are
+    /// offset by one, so no derived stream repeats the stream of the root sampler.
+    ///
+    /// # Arguments
+    /// - stream: the stream id, typically the index of the sequence in the batch
+    ///
+    /// # Returns
+    /// - the derived sampler
+    ///
+    pub fn derive_stream(&self, stream: u64) -> CodonSampler {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(stream.wrapping_add(1));
+
+        CodonSampler {
+            tables: Arc::clone(&self.tables),
+            seed: self.seed,
+            rng,
+        }
+    }
+
+    ///
+    /// Select a codon for a residue according to the weights in the usage data
+    ///
+    /// # Arguments
+    /// - residue to select for
+    ///
+    /// # Returns
+    /// - selected codon
+    ///
+    pub fn sample(&mut self, residue: char) -> Result<Codon> {
+        match self.tables.get(&residue) {
+            Some(table) => Ok(table.sample(&mut self.rng)),
+            None => anyhow::bail!("Invalid residue passed in: {residue}"),
+        }
+    }
+
+    ///
+    /// Select a codon for every residue of a protein sequence
+    ///
+    pub fn sample_sequence(&mut self, protein: &str) -> Result<Vec<Codon>> {
+        protein
+            .chars()
+            .map(|residue| self.sample(residue))
+            .collect()
+    }
+
+    ///
+    /// Access the underlying RNG, for algorithms that need randomness beyond codon selection
+    ///
+    pub fn rng(&mut self) -> &mut ChaCha8Rng {
+        &mut self.rng
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([(
+            'A',
+            HashMap::from([
+                (Codon::GCT, 0.1),
+                (Codon::GCC, 0.2),
+                (Codon::GCA, 0.3),
+                (Codon::GCG, 0.4),
+            ]),
+        )])
+    }
+
+    #[rstest]
+    fn test_repeated_residues_get_different_codons(usage: CodonUsageByResidue) {
+        let mut sampler = CodonSampler::new(&usage, 42).unwrap();
+        let codons = sampler.sample_sequence(&"A".repeat(100)).unwrap();
+
+        assert_eq!(codons.iter().all(|c| *c == codons[0]), false);
+    }
+
+    #[rstest]
+    fn test_sampler_is_reproducible(usage: CodonUsageByResidue) {
+        let first = CodonSampler::new(&usage, 7)
+            .unwrap()
+            .sample_sequence("AAAAAAAAAA")
+            .unwrap();
+        let second = CodonSampler::new(&usage, 7)
+            .unwrap()
+            .sample_sequence("AAAAAAAAAA")
+            .unwrap();
+        assert_eq!(first, second);
+
+        // derived streams are reproducible too, but differ from each other
+        let sampler = CodonSampler::new(&usage, 7).unwrap();
+        let stream1 = sampler.derive_stream(1).sample_sequence(&"A".repeat(50));
+        let stream1_again = sampler.derive_stream(1).sample_sequence(&"A".repeat(50));
+        let stream2 = sampler.derive_stream(2).sample_sequence(&"A".repeat(50));
+        assert_eq!(stream1.as_ref().unwrap(), stream1_again.as_ref().unwrap());
+        assert_eq!(stream1.unwrap() == stream2.unwrap(), false);
+
+        // stream 0 doesn't repeat the root sampler
+        let root = CodonSampler::new(&usage, 7)
+            .unwrap()
+            .sample_sequence(&"A".repeat(50));
+        let stream0 = sampler.derive_stream(0).sample_sequence(&"A".repeat(50));
+        assert_eq!(root.unwrap() == stream0.unwrap(), false);
+    }
+
+    #[rstest]
+    fn test_alias_table_matches_distribution(usage: CodonUsageByResidue) {
+        let table = AliasTable::new(usage.get(&'A').unwrap()).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+        let num_samples = 100_000;
+        let mut counts: HashMap<Codon, usize> = HashMap::new();
+        for _ in 0..num_samples {
+            *counts.entry(table.sample(&mut rng)).or_insert(0) += 1;
+        }
+
+        for (codon, pref) in usage.get(&'A').unwrap() {
+            let observed = *counts.get(codon).unwrap() as f64 / num_samples as f64;
+            assert_eq!((observed - pref).abs() < 0.01, true);
+        }
+    }
+}
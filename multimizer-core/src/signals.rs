@@ -0,0 +1,399 @@
+use crate::constraints::{SequenceConstraint, Violation};
+use crate::utils::find_iupac_matches;
+
+///
+/// Constraint against internal ribosome binding sites in bacterial hosts: Shine-Dalgarno-like
+/// motifs a few bases upstream of an ATG or GTG, in any frame, which can start translation
+/// in the middle of the gene.
+///
+/// Every window of the motif's length is scored by the number of positions that match the
+/// motif; windows with at least `min_matches` are RBS-like. A violation is an RBS-like window
+/// with one or more start codons `min_spacing..=max_spacing` nucleotides after it.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShineDalgarnoConstraint {
+    pub motif: String,
+    pub min_matches: usize,
+    pub min_spacing: usize,
+    pub max_spacing: usize,
+}
+
+impl Default for ShineDalgarnoConstraint {
+    fn default() -> Self {
+        ShineDalgarnoConstraint {
+            motif: "AGGAGG".to_string(),
+            min_matches: 5,
+            min_spacing: 4,
+            max_spacing: 12,
+        }
+    }
+}
+
+impl ShineDalgarnoConstraint {
+    ///
+    /// Score a window of the sequence against the motif
+    ///
+    /// # Arguments
+    /// - window, the same length as the motif
+    ///
+    /// # Returns
+    /// - number of matching positions
+    ///
+    pub fn score(&self, window: &[u8]) -> usize {
+        window
+            .iter()
+            .zip(self.motif.as_bytes())
+            .filter(|(base, motif_base)| base.eq_ignore_ascii_case(motif_base))
+            .count()
+    }
+}
+
+impl SequenceConstraint for ShineDalgarnoConstraint {
+    fn name(&self) -> &str {
+        "shine-dalgarno"
+    }
+
+    fn context(&self) -> Option<usize> {
+        // the motif, the spacer and the start codon
+        Some(self.motif.len() + self.max_spacing + 3)
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let bases = dna.to_uppercase().into_bytes();
+        let motif_length = self.motif.len();
+        if motif_length == 0 || bases.len() < motif_length {
+            return vec![];
+        }
+
+        let mut violations = vec![];
+        for (start, window) in bases.windows(motif_length).enumerate() {
+            let score = self.score(window);
+            if score < self.min_matches {
+                continue;
+            }
+
+            let motif_end = start + motif_length;
+            let starts: Vec<usize> = (motif_end + self.min_spacing..=motif_end + self.max_spacing)
+                .filter(|position| {
+                    matches!(
+                        bases.get(*position..*position + 3),
+                        Some(b"ATG") | Some(b"GTG")
+                    )
+                })
+                .collect();
+            let Some(last_start) = starts.last() else {
+                continue;
+            };
+
+            // a stronger motif or more start codons make the violation worse
+            violations.push(Violation {
+                constraint: self.name().to_string(),
+                start,
+                end: last_start + 3,
+                penalty: ((score + 1 - self.min_matches) * starts.len()) as f64,
+                description: format!(
+                    "RBS-like motif {} ({score}/{motif_length}) at position {start} upstream of start codon(s) at {starts:?}",
+                    String::from_utf8_lossy(window),
+                ),
+            });
+        }
+
+        violations
+    }
+}
+
+/// Nucleotide frequencies (A, C, G, T) at the 9 positions of a splice donor, 3 exonic and
+/// 6 intronic (MAG|GTRAGT)
+const DONOR_FREQUENCIES: [[f64; 4]; 9] = [
+    [0.33, 0.36, 0.19, 0.12],
+    [0.60, 0.13, 0.14, 0.13],
+    [0.08, 0.03, 0.81, 0.08],
+    [0.00, 0.00, 1.00, 0.00],
+    [0.00, 0.00, 0.00, 1.00],
+    [0.52, 0.03, 0.42, 0.03],
+    [0.71, 0.08, 0.12, 0.09],
+    [0.07, 0.05, 0.79, 0.09],
+    [0.16, 0.15, 0.20, 0.49],
+];
+
+/// Nucleotide frequencies (A, C, G, T) at the 15 positions of a splice acceptor: the
+/// polypyrimidine tract, YAG and the first exonic base (YYYYYYYYYYNCAG|G)
+const ACCEPTOR_FREQUENCIES: [[f64; 4]; 15] = [
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.10, 0.33, 0.10, 0.47],
+    [0.25, 0.30, 0.20, 0.25],
+    [0.06, 0.70, 0.02, 0.22],
+    [1.00, 0.00, 0.00, 0.00],
+    [0.00, 0.00, 1.00, 0.00],
+    [0.24, 0.14, 0.52, 0.10],
+    [0.25, 0.25, 0.25, 0.25],
+];
+
+/// Log-odds score (bits, against a uniform background) of a window, None for impossible sites
+fn score_pwm(window: &[u8], frequencies: &[[f64; 4]]) -> Option<f64> {
+    let mut score = 0.0;
+    for (base, position) in window.iter().zip(frequencies) {
+        let frequency = match base.to_ascii_uppercase() {
+            b'A' => position[0],
+            b'C' => position[1],
+            b'G' => position[2],
+            b'T' => position[3],
+            _ => return None,
+        };
+        if frequency == 0.0 {
+            return None;
+        }
+        score += (frequency / 0.25).log2();
+    }
+    Some(score)
+}
+
+///
+/// Constraint against cryptic splice sites in eukaryotic hosts. Every GT is scored as a
+/// donor and every AG as an acceptor with a position weight matrix; sites scoring at or above
+/// the thresholds are violations. The best possible scores are about 12.7 (donor) and 15.6
+/// (acceptor) bits.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpliceSiteConstraint {
+    pub min_donor_score: f64,
+    pub min_acceptor_score: f64,
+}
+
+impl Default for SpliceSiteConstraint {
+    fn default() -> Self {
+        SpliceSiteConstraint {
+            min_donor_score: 8.0,
+            min_acceptor_score: 10.0,
+        }
+    }
+}
+
+impl SpliceSiteConstraint {
+    ///
+    /// Score a potential donor site. The window starts 3 nucleotides before the GT.
+    ///
+    /// # Returns
+    /// - the score in bits, or None if the window is not GT-anchored
+    ///
+    pub fn score_donor(&self, window: &[u8]) -> Option<f64> {
+        score_pwm(window, &DONOR_FREQUENCIES)
+    }
+
+    ///
+    /// Score a potential acceptor site. The window ends 2 nucleotides after the AG.
+    ///
+    /// # Returns
+    /// - the score in bits, or None if the window is not AG-anchored
+    ///
+    pub fn score_acceptor(&self, window: &[u8]) -> Option<f64> {
+        score_pwm(window, &ACCEPTOR_FREQUENCIES)
+    }
+}
+
+impl SequenceConstraint for SpliceSiteConstraint {
+    fn name(&self) -> &str {
+        "splice-site"
+    }
+
+    fn context(&self) -> Option<usize> {
+        Some(DONOR_FREQUENCIES.len().max(ACCEPTOR_FREQUENCIES.len()))
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        let bases = dna.as_bytes();
+        let mut violations = vec![];
+
+        let sites: [(&str, &[[f64; 4]], f64); 2] = [
+            ("donor", &DONOR_FREQUENCIES, self.min_donor_score),
+            ("acceptor", &ACCEPTOR_FREQUENCIES, self.min_acceptor_score),
+        ];
+        for (kind, frequencies, min_score) in sites {
+            let length = frequencies.len();
+            for (start, window) in bases.windows(length).enumerate() {
+                let score = score_pwm(window, frequencies);
+                let Some(score) = score.filter(|score| *score >= min_score) else {
+                    continue;
+                };
+                violations.push(Violation {
+                    constraint: self.name().to_string(),
+                    start,
+                    end: start + length,
+                    penalty: score - min_score + 1.0,
+                    description: format!(
+                        "cryptic splice {kind} {} at position {start} (score {score:.2})",
+                        String::from_utf8_lossy(window)
+                    ),
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+///
+/// Constraint against polyadenylation signals (AATAAA and its most common variant ATTAAA
+/// by default) in eukaryotic hosts. Only the coding strand is checked.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyadenylationSignalConstraint {
+    pub signals: Vec<String>,
+}
+
+impl Default for PolyadenylationSignalConstraint {
+    fn default() -> Self {
+        PolyadenylationSignalConstraint {
+            signals: vec!["AATAAA".to_string(), "ATTAAA".to_string()],
+        }
+    }
+}
+
+impl SequenceConstraint for PolyadenylationSignalConstraint {
+    fn name(&self) -> &str {
+        "polyadenylation-signal"
+    }
+
+    fn context(&self) -> Option<usize> {
+        self.signals.iter().map(|signal| signal.len()).max()
+    }
+
+    fn find_violations(&self, dna: &str) -> Vec<Violation> {
+        self.signals
+            .iter()
+            .flat_map(|signal| {
+                find_iupac_matches(dna, signal)
+                    .into_iter()
+                    .map(move |start| Violation {
+                        constraint: self.name().to_string(),
+                        start,
+                        end: start + signal.len(),
+                        penalty: 1.0,
+                        description: format!("polyA signal {signal} at position {start}"),
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use crate::constraints::enforce_constraints;
+    use crate::models::Codon;
+    use crate::optimizations::CodonUsageByResidue;
+    use crate::utils::split_into_codons;
+
+    #[rstest]
+    fn test_shine_dalgarno_constraint() {
+        let usage: CodonUsageByResidue = HashMap::from([
+            ('K', HashMap::from([(Codon::AAA, 0.3), (Codon::AAG, 0.7)])),
+            (
+                'R',
+                HashMap::from([
+                    (Codon::CGT, 0.1),
+                    (Codon::CGC, 0.1),
+                    (Codon::CGA, 0.1),
+                    (Codon::CGG, 0.1),
+                    (Codon::AGA, 0.2),
+                    (Codon::AGG, 0.4),
+                ]),
+            ),
+            ('M', HashMap::from([(Codon::ATG, 1.0)])),
+        ]);
+        let constraint = ShineDalgarnoConstraint::default();
+
+        // AGGAGG, six bases of spacer and an in-frame ATG
+        let dna = "AGGAGGAAAAAGATG";
+        let violations = constraint.find_violations(dna);
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].start, violations[0].end), (0, 15));
+
+        // no start codon downstream, no violation
+        assert_eq!(constraint.find_violations("AGGAGGAAAAAGAAA"), vec![]);
+
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![Box::new(constraint)];
+        let mut codons = split_into_codons(dna).unwrap();
+        let remaining = enforce_constraints(&mut codons, &usage, &constraints, &[]).violations;
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(remaining, vec![]);
+        assert_eq!(dna.ends_with("ATG"), true);
+    }
+
+    #[rstest]
+    fn test_splice_site_constraint() {
+        let constraint = SpliceSiteConstraint::default();
+
+        // consensus donor CAG|GTAAGT and a strong acceptor
+        assert_eq!(constraint.score_donor(b"CAGGTAAGT").unwrap() > 12.0, true);
+        assert_eq!(constraint.score_donor(b"CAGCTAAGT"), None);
+        assert_eq!(
+            constraint.score_acceptor(b"TTTTTTTTTCCAGGA").unwrap() > 10.0,
+            true
+        );
+
+        let violations = constraint.find_violations("AAACAGGTAAGTAAA");
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].start, violations[0].end), (3, 12));
+    }
+
+    #[rstest]
+    fn test_remove_eukaryotic_signals() {
+        let usage: CodonUsageByResidue = HashMap::from([
+            ('K', HashMap::from([(Codon::AAA, 0.7), (Codon::AAG, 0.3)])),
+            ('N', HashMap::from([(Codon::AAT, 0.6), (Codon::AAC, 0.4)])),
+            ('Q', HashMap::from([(Codon::CAA, 0.3), (Codon::CAG, 0.7)])),
+            (
+                'V',
+                HashMap::from([
+                    (Codon::GTT, 0.2),
+                    (Codon::GTC, 0.2),
+                    (Codon::GTA, 0.1),
+                    (Codon::GTG, 0.5),
+                ]),
+            ),
+            (
+                'S',
+                HashMap::from([
+                    (Codon::TCT, 0.1),
+                    (Codon::TCC, 0.1),
+                    (Codon::TCA, 0.1),
+                    (Codon::TCG, 0.1),
+                    (Codon::AGT, 0.4),
+                    (Codon::AGC, 0.2),
+                ]),
+            ),
+        ]);
+        let constraints: Vec<Box<dyn SequenceConstraint>> = vec![
+            Box::new(SpliceSiteConstraint::default()),
+            Box::new(PolyadenylationSignalConstraint::default()),
+        ];
+
+        // AAT AAA contains a polyA signal, CAG GTA AGT a consensus donor
+        let mut codons = split_into_codons("AATAAACAGGTAAGT").unwrap();
+        let report = enforce_constraints(&mut codons, &usage, &constraints, &[]);
+        let dna: String = codons.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(report.violations, vec![]);
+        assert_eq!(dna.contains("AATAAA"), false);
+        assert_eq!(report.changes.is_empty(), false);
+        for change in &report.changes {
+            assert_eq!(change.position % 3, 0);
+            assert_eq!(change.position < dna.len(), true);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::models::Codon;
+use crate::optimizations::CodonUsageByResidue;
+use crate::sampling::CodonSampler;
+
+///
+/// A strategy for choosing a codon for every residue of a protein sequence.
+///
+/// Strategies are plugged into the optimizers through `OptimizationOptions::strategy`.
+/// Custom strategies can be implemented outside of this crate.
+///
+pub trait OptimizationStrategy: Send + Sync {
+    /// Human readable name of the strategy
+    fn name(&self) -> &str;
+
+    /// Whether the strategy always produces the same sequence for the same input.
+    /// Deterministic strategies only need a single iteration of the optimizer.
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+
+    ///
+    /// Generate a candidate sequence
+    ///
+    /// # Arguments
+    /// - protein: the protein sequence to back-translate
+    /// - usage: codon usage data for the target organism
+    /// - sampler: seeded sampler for the usage data, for strategies that need randomness
+    ///
+    /// # Returns
+    /// - one codon per residue
+    ///
+    fn generate(
+        &self,
+        protein: &str,
+        usage: &CodonUsageByResidue,
+        sampler: &mut CodonSampler,
+    ) -> Result<Vec<Codon>>;
+}
+
+///
+/// Sample every codon at random, weighted by its usage.
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WeightedRandomStrategy;
+
+impl OptimizationStrategy for WeightedRandomStrategy {
+    fn name(&self) -> &str {
+        "weighted-random"
+    }
+
+    fn generate(
+        &self,
+        protein: &str,
+        _usage: &CodonUsageByResidue,
+        sampler: &mut CodonSampler,
+    ) -> Result<Vec<Codon>> {
+        sampler.sample_sequence(protein)
+    }
+}
+
+///
+/// "One amino acid, one codon" -- always use the most frequent codon for each residue.
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MostFrequentCodonStrategy;
+
+impl OptimizationStrategy for MostFrequentCodonStrategy {
+    fn name(&self) -> &str {
+        "most-frequent"
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn generate(
+        &self,
+        protein: &str,
+        usage: &CodonUsageByResidue,
+        _sampler: &mut CodonSampler,
+    ) -> Result<Vec<Codon>> {
+        protein
+            .chars()
+            .map(|residue| {
+                let preferences = get_preferences(residue, usage)?;
+                // ties are broken by codon order so the result is stable
+                let best = preferences
+                    .iter()
+                    .max_by(|(c1, p1), (c2, p2)| p1.total_cmp(p2).then(c2.cmp(c1)))
+                    .map(|(codon, _)| *codon);
+                best.ok_or_else(|| anyhow::anyhow!("No codons available for residue {residue}"))
+            })
+            .collect()
+    }
+}
+
+///
+/// Deterministic, quota-based strategy that matches the usage distribution as closely as possible.
+///
+/// Each occurrence of a residue is assigned the codon that is furthest behind its target
+/// count so far, so a residue that occurs `n` times ends up with roughly `n * pref` copies
+/// of every codon, spread evenly along the sequence.
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MatchDistributionStrategy;
+
+impl OptimizationStrategy for MatchDistributionStrategy {
+    fn name(&self) -> &str {
+        "match-distribution"
+    }
+
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    fn generate(
+        &self,
+        protein: &str,
+        usage: &CodonUsageByResidue,
+        _sampler: &mut CodonSampler,
+    ) -> Result<Vec<Codon>> {
+        let mut seen: HashMap<char, f64> = HashMap::new();
+        let mut used: HashMap<Codon, f64> = HashMap::new();
+        let mut codons = Vec::with_capacity(protein.len());
+
+        for residue in protein.chars() {
+            let preferences = get_preferences(residue, usage)?;
+            let total: f64 = preferences.iter().map(|(_, p)| p).sum();
+            if total <= 0.0 {
+                anyhow::bail!("No codons available for residue {residue}")
+            }
+
+            let occurrence = seen.entry(residue).or_insert(0.0);
+            *occurrence += 1.0;
+
+            let deficit = |codon: &Codon, pref: f64| {
+                pref / total * *occurrence - used.get(codon).unwrap_or(&0.0)
+            };
+
+            let chosen = preferences
+                .iter()
+                .max_by(|(c1, p1), (c2, p2)| {
+                    deficit(c1, *p1)
+                        .total_cmp(&deficit(c2, *p2))
+                        .then(p1.total_cmp(p2))
+                        .then(c2.cmp(c1))
+                })
+                .map(|(codon, _)| *codon)
+                .unwrap();
+
+            *used.entry(chosen).or_insert(0.0) += 1.0;
+            codons.push(chosen);
+        }
+
+        Ok(codons)
+    }
+}
+
+fn get_preferences(residue: char, usage: &CodonUsageByResidue) -> Result<Vec<(Codon, f64)>> {
+    match usage.get(&residue) {
+        Some(preferences) if !preferences.is_empty() => {
+            Ok(preferences.iter().map(|(c, p)| (*c, *p)).collect())
+        }
+        _ => anyhow::bail!("Invalid residue passed in: {residue}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn usage() -> CodonUsageByResidue {
+        HashMap::from([
+            (
+                'A',
+                HashMap::from([
+                    (Codon::GCT, 0.1),
+                    (Codon::GCC, 0.2),
+                    (Codon::GCA, 0.3),
+                    (Codon::GCG, 0.4),
+                ]),
+            ),
+            ('M', HashMap::from([(Codon::ATG, 1.0)])),
+        ])
+    }
+
+    #[rstest]
+    fn test_most_frequent_codon(usage: CodonUsageByResidue) {
+        let mut sampler = CodonSampler::new(&usage, 42).unwrap();
+        let codons = MostFrequentCodonStrategy
+            .generate("MAAA", &usage, &mut sampler)
+            .unwrap();
+
+        assert_eq!(codons, vec![Codon::ATG, Codon::GCG, Codon::GCG, Codon::GCG]);
+    }
+
+    #[rstest]
+    fn test_match_distribution(usage: CodonUsageByResidue) {
+        let mut sampler = CodonSampler::new(&usage, 42).unwrap();
+        let codons = MatchDistributionStrategy
+            .generate(&"A".repeat(10), &usage, &mut sampler)
+            .unwrap();
+
+        let count = |codon: Codon| codons.iter().filter(|c| **c == codon).count();
+        assert_eq!(count(Codon::GCT), 1);
+        assert_eq!(count(Codon::GCC), 2);
+        assert_eq!(count(Codon::GCA), 3);
+        assert_eq!(count(Codon::GCG), 4);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::constraints::{
+    GcContentConstraint, GcWindowConstraint, LowComplexityConstraint, SequenceConstraint, Violation,
+};
+use crate::repeats::{find_repeats, RepeatKind};
+
+///
+/// Thresholds for the synthesis complexity checks. The defaults follow the limits commonly
+/// given by gene synthesis vendors.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthesisComplexityOptions {
+    /// Allowed GC content of the whole sequence
+    pub gc_min: f64,
+    pub gc_max: f64,
+    /// Allowed GC content of every window of `gc_window_size` nucleotides
+    pub gc_window_size: usize,
+    pub gc_window_min: f64,
+    pub gc_window_max: f64,
+    /// Longest homopolymer allowed
+    pub max_homopolymer: usize,
+    /// Shortest direct or inverted repeat that is reported
+    pub min_repeat_length: usize,
+    /// Shortest hairpin stem that is reported
+    pub min_hairpin_stem: usize,
+    /// Longest loop between the two arms of a hairpin
+    pub max_hairpin_loop: usize,
+    /// Allowed sequence length
+    pub min_length: usize,
+    pub max_length: usize,
+}
+
+impl Default for SynthesisComplexityOptions {
+    fn default() -> Self {
+        SynthesisComplexityOptions {
+            gc_min: 0.25,
+            gc_max: 0.65,
+            gc_window_size: 100,
+            gc_window_min: 0.25,
+            gc_window_max: 0.65,
+            max_homopolymer: 8,
+            min_repeat_length: 20,
+            min_hairpin_stem: 12,
+            max_hairpin_loop: 50,
+            min_length: 150,
+            max_length: 5_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SynthesisIssueKind {
+    GcContent,
+    GcWindow,
+    Homopolymer,
+    Repeat,
+    Hairpin,
+    Length,
+}
+
+///
+/// A single problem found by `synthesis_complexity`. Positions are 0-based, end-exclusive.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthesisIssue {
+    pub kind: SynthesisIssueKind,
+    pub start: usize,
+    pub end: usize,
+    /// Contribution of the issue to the overall score
+    pub severity: f64,
+    pub description: String,
+}
+
+///
+/// Result of the synthesis complexity analysis
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthesisComplexity {
+    /// Sum of the severities of all issues -- 0 means nothing was found, higher is harder
+    pub score: f64,
+    pub issues: Vec<SynthesisIssue>,
+}
+
+fn issues_from_violations(
+    kind: SynthesisIssueKind,
+    violations: Vec<Violation>,
+) -> impl Iterator<Item = SynthesisIssue> {
+    violations.into_iter().map(move |violation| SynthesisIssue {
+        kind,
+        start: violation.start,
+        end: violation.end,
+        severity: violation.penalty,
+        description: violation.description,
+    })
+}
+
+/// Merge overlapping issues of one kind into one issue per region, as severe as the worst of
+/// them
+fn merge_overlapping(mut issues: Vec<SynthesisIssue>) -> Vec<SynthesisIssue> {
+    issues.sort_by_key(|issue| (issue.start, issue.end));
+
+    let mut regions: Vec<Vec<SynthesisIssue>> = vec![];
+    for issue in issues {
+        match regions.last_mut() {
+            Some(region) if issue.start < region.iter().map(|i| i.end).max().unwrap() => {
+                region.push(issue)
+            }
+            _ => regions.push(vec![issue]),
+        }
+    }
+
+    regions
+        .into_iter()
+        .map(|region| {
+            let count = region.len();
+            let start = region[0].start;
+            let end = region.iter().map(|issue| issue.end).max().unwrap();
+            let worst = region
+                .into_iter()
+                .max_by(|i1, i2| i1.severity.total_cmp(&i2.severity))
+                .unwrap();
+            let description = if count > 1 {
+                format!("{count} overlapping, worst: {}", worst.description)
+            } else {
+                worst.description
+            };
+            SynthesisIssue {
+                start,
+                end,
+                description,
+                ..worst
+            }
+        })
+        .collect()
+}
+
+///
+/// Check how hard a DNA sequence will be to synthesize: GC content extremes (overall and in
+/// sliding windows), homopolymers, long direct and inverted repeats, hairpins and length.
+///
+/// Every issue has a severity, in excess nucleotides -- how many G/C bases too many or too
+/// few (in the worst window, for a region of overlapping GC windows), how far a homopolymer or repeat goes over its limit, or how many bases the sequence is
+/// too short or long. The score is the sum of all severities. Overlapping issues of a kind
+/// (the copies of a tandem repeat, the hairpins of a long inverted repeat, neighbouring GC
+/// windows) are merged into one issue per region, as severe as the worst of them, and runs of
+/// a single base only count as homopolymers.
+///
+/// # Arguments
+/// - dna sequence
+/// - thresholds for the checks
+///
+/// # Returns
+/// - the score and the itemized issues, ordered by kind and position
+///
+pub fn synthesis_complexity(
+    dna: &str,
+    options: &SynthesisComplexityOptions,
+) -> SynthesisComplexity {
+    let dna = dna.to_uppercase();
+    let mut issues: Vec<SynthesisIssue> = vec![];
+
+    let gc_content = GcContentConstraint {
+        min: options.gc_min,
+        max: options.gc_max,
+    };
+    // penalties are fractions of the sequence, severities are in nucleotides
+    issues.extend(
+        issues_from_violations(
+            SynthesisIssueKind::GcContent,
+            gc_content.find_violations(&dna),
+        )
+        .map(|issue| SynthesisIssue {
+            severity: issue.severity * dna.len() as f64,
+            ..issue
+        }),
+    );
+
+    let gc_window = GcWindowConstraint {
+        window_size: options.gc_window_size.max(1),
+        min: options.gc_window_min,
+        max: options.gc_window_max,
+    };
+    let window_size = gc_window.window_size.min(dna.len());
+    let gc_windows = issues_from_violations(
+        SynthesisIssueKind::GcWindow,
+        gc_window.find_violations(&dna),
+    )
+    .map(|issue| SynthesisIssue {
+        severity: issue.severity * window_size as f64,
+        ..issue
+    });
+    issues.extend(merge_overlapping(gc_windows.collect()));
+
+    // tandem repeats are covered by the repeat check below
+    let homopolymers =
+        LowComplexityConstraint::new(options.max_homopolymer, usize::MAX, usize::MAX);
+    issues.extend(issues_from_violations(
+        SynthesisIssueKind::Homopolymer,
+        homopolymers.find_violations(&dna),
+    ));
+
+    let min_length = options
+        .min_repeat_length
+        .min(options.min_hairpin_stem)
+        .max(1);
+    let (mut repeats, mut hairpins) = (vec![], vec![]);
+    for repeat in find_repeats(&dna, min_length) {
+        // runs of a single base are left to the homopolymer check
+        let region = &dna.as_bytes()[repeat.first..repeat.second + repeat.length];
+        if region.iter().all(|base| *base == region[0]) {
+            continue;
+        }
+
+        let loop_length = repeat.second.saturating_sub(repeat.first + repeat.length);
+        let (kind, limit) = match repeat.kind {
+            RepeatKind::Inverted
+                if loop_length <= options.max_hairpin_loop
+                    && repeat.length >= options.min_hairpin_stem =>
+            {
+                (SynthesisIssueKind::Hairpin, options.min_hairpin_stem)
+            }
+            _ if repeat.length >= options.min_repeat_length => {
+                (SynthesisIssueKind::Repeat, options.min_repeat_length)
+            }
+            _ => continue,
+        };
+        let description = match kind {
+            SynthesisIssueKind::Hairpin => format!(
+                "hairpin with {} bp stem and {loop_length} bp loop at position {}",
+                repeat.length, repeat.first
+            ),
+            _ => format!(
+                "{:?} repeat of {} bp at positions {} and {}",
+                repeat.kind, repeat.length, repeat.first, repeat.second
+            ),
+        };
+        let issue = SynthesisIssue {
+            kind,
+            start: repeat.first,
+            end: repeat.second + repeat.length,
+            severity: (repeat.length + 1 - limit) as f64,
+            description,
+        };
+        match kind {
+            SynthesisIssueKind::Hairpin => hairpins.push(issue),
+            _ => repeats.push(issue),
+        }
+    }
+    // a tandem repeat is found once for every offset between its copies, and a stretch of
+    // inverted repeats folds into many hairpins -- each region counts once
+    issues.extend(merge_overlapping(repeats));
+    issues.extend(merge_overlapping(hairpins));
+
+    let length = dna.len();
+    if length < options.min_length || length > options.max_length {
+        let excess = if length < options.min_length {
+            options.min_length - length
+        } else {
+            length - options.max_length
+        };
+        issues.push(SynthesisIssue {
+            kind: SynthesisIssueKind::Length,
+            start: 0,
+            end: length,
+            severity: excess as f64,
+            description: format!(
+                "length of {length} bp outside {} - {} bp",
+                options.min_length, options.max_length
+            ),
+        });
+    }
+
+    SynthesisComplexity {
+        score: issues.iter().map(|issue| issue.severity).sum(),
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_synthesis_complexity() {
+        let options = SynthesisComplexityOptions {
+            gc_window_size: 20,
+            min_repeat_length: 12,
+            min_hairpin_stem: 8,
+            min_length: 10,
+            ..Default::default()
+        };
+
+        // a balanced sequence without problems
+        let clean = "ATGGCTAGCAAGGTTCTGACCGAAATCCGTTACGAT";
+        let result = synthesis_complexity(clean, &options);
+        assert_eq!(result.issues, vec![]);
+        assert_eq!(result.score, 0.0);
+
+        // a hairpin (GCTAGCATCC ... GGATGCTAGC) and a run of ten A's
+        let messy = "ATGGCTAGCATCCTTGGATGCTAGCTTAAAAAAAAAACTGACCGAAAT";
+        let kinds: Vec<SynthesisIssueKind> = synthesis_complexity(messy, &options)
+            .issues
+            .iter()
+            .map(|issue| issue.kind)
+            .collect();
+        assert_eq!(kinds.contains(&SynthesisIssueKind::Hairpin), true);
+        assert_eq!(kinds.contains(&SynthesisIssueKind::Homopolymer), true);
+
+        let homopolymer = synthesis_complexity(messy, &options)
+            .issues
+            .into_iter()
+            .find(|issue| issue.kind == SynthesisIssueKind::Homopolymer)
+            .unwrap();
+        assert_eq!((homopolymer.start, homopolymer.end), (27, 37));
+
+        // a GC-only stretch is one GC window issue, 7 G/C bases over 65% of 20 bp
+        let gc_rich = format!("{clean}{}{clean}", "GGC".repeat(10));
+        let gc_windows: Vec<SynthesisIssue> = synthesis_complexity(&gc_rich, &options)
+            .issues
+            .into_iter()
+            .filter(|issue| issue.kind == SynthesisIssueKind::GcWindow)
+            .collect();
+        assert_eq!(gc_windows.len(), 1);
+        assert_eq!((gc_windows[0].severity - 7.0).abs() < 1e-9, true);
+
+        // a tandem repeat is one issue, scored by its longest copy: 196 bp against 20 bp
+        let tandem = synthesis_complexity(&"ATGC".repeat(50), &Default::default());
+        let repeats: Vec<&SynthesisIssue> = tandem
+            .issues
+            .iter()
+            .filter(|issue| issue.kind == SynthesisIssueKind::Repeat)
+            .collect();
+        assert_eq!(repeats.len(), 1);
+        assert_eq!((repeats[0].start, repeats[0].end), (0, 200));
+        assert_eq!(repeats[0].severity, 177.0);
+
+        // a homopolymer is only reported as one
+        let poly_a = synthesis_complexity(&"A".repeat(30), &options);
+        let kinds: Vec<SynthesisIssueKind> = poly_a.issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SynthesisIssueKind::GcContent,
+                SynthesisIssueKind::GcWindow,
+                SynthesisIssueKind::Homopolymer,
+            ]
+        );
+
+        // too short
+        let result = synthesis_complexity("ATG", &options);
+        assert_eq!(
+            result.issues.last().unwrap().kind,
+            SynthesisIssueKind::Length
+        );
+    }
+}
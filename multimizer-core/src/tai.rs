@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::consts::{AACodonLibrary, CodonToAA, VALID_NUCLEOTIDES};
+use crate::models::Codon;
+use crate::optimizations::{
+    optimize_for_single_organism, CodonUsageByResidue, OptimizationOptions, OptimizationResult,
+};
+use crate::utils::{complement, split_into_codons, RelativeAdaptivenessTable};
+
+/// tRNA gene copy numbers, keyed by anticodon (5' to 3', e.g. `GCC` for the glycine codon GGC)
+pub type TrnaGeneCopyNumbers = HashMap<String, u32>;
+
+///
+/// Selective constraints on wobble pairing between the first anticodon base and the third
+/// codon base -- the higher the value, the less efficient the pairing. Watson-Crick pairs
+/// have no constraint. The defaults are the values fitted by dos Reis et al. (2004).
+///
+/// By default adenosine at the wobble position is read as inosine, as it is in the tRNAs of
+/// eukaryotes. Bacteria only modify tRNA-Arg(ACG), see `WobbleWeights::bacterial`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct WobbleWeights {
+    /// G in the anticodon pairing with U (T) in the codon
+    pub g_u: f64,
+    /// Inosine pairing with C
+    pub i_c: f64,
+    /// Inosine pairing with A
+    pub i_a: f64,
+    /// U in the anticodon pairing with G in the codon
+    pub u_g: f64,
+    /// Whether every adenosine at the wobble position is modified to inosine, or only the
+    /// one of tRNA-Arg(ACG)
+    pub adenosine_to_inosine: bool,
+}
+
+impl WobbleWeights {
+    ///
+    /// Weights for bacteria, where only tRNA-Arg(ACG) carries inosine at the wobble position
+    ///
+    pub fn bacterial() -> Self {
+        WobbleWeights {
+            adenosine_to_inosine: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for WobbleWeights {
+    fn default() -> Self {
+        WobbleWeights {
+            g_u: 0.41,
+            i_c: 0.28,
+            i_a: 0.9999,
+            u_g: 0.68,
+            adenosine_to_inosine: true,
+        }
+    }
+}
+
+/// Clean up the anticodons of the copy numbers, accepting RNA notation
+fn normalize_copy_numbers(copy_numbers: &TrnaGeneCopyNumbers) -> Result<HashMap<String, f64>> {
+    let mut normalized: HashMap<String, f64> = HashMap::new();
+    for (anticodon, count) in copy_numbers {
+        let anticodon = anticodon.to_uppercase().replace('U', "T");
+        if anticodon.len() != 3 || !anticodon.chars().all(|c| VALID_NUCLEOTIDES.contains(c)) {
+            anyhow::bail!("Invalid anticodon: {anticodon}")
+        }
+        *normalized.entry(anticodon).or_insert(0.0) += *count as f64;
+    }
+    Ok(normalized)
+}
+
+///
+/// Compute the tRNA adaptation weight (w) of every sense codon, following dos Reis et al.
+/// (2004). The absolute adaptiveness of a codon is the sum of the copy numbers of every tRNA
+/// that decodes it, discounted by the wobble weights, and w is that divided by the highest
+/// absolute adaptiveness. Codons no tRNA decodes get the geometric mean of the other weights.
+///
+/// # Arguments
+/// - tRNA gene copy numbers by anticodon
+/// - wobble pairing weights
+///
+/// # Returns
+/// - the weights of all 61 sense codons
+///
+pub fn compute_tai_weights(
+    copy_numbers: &TrnaGeneCopyNumbers,
+    wobble: &WobbleWeights,
+) -> Result<RelativeAdaptivenessTable> {
+    let copy_numbers = normalize_copy_numbers(copy_numbers)?;
+    let codon_to_aa = CodonToAA::new();
+
+    let mut absolute: HashMap<Codon, f64> = HashMap::new();
+    for codon in Codon::ALL {
+        if codon_to_aa.convert(&codon) == Some('*') {
+            continue;
+        }
+        let bases: Vec<char> = codon.to_string().chars().collect();
+        let tgcn = |wobble_base: char| -> f64 {
+            let anticodon = format!(
+                "{wobble_base}{}{}",
+                complement(bases[1] as u8) as char,
+                complement(bases[0] as u8) as char
+            );
+            copy_numbers.get(&anticodon).copied().unwrap_or(0.0)
+        };
+
+        // an unmodified adenosine only pairs with U (T)
+        let inosine = wobble.adenosine_to_inosine || (bases[0] == 'C' && bases[1] == 'G');
+        let tgcn_inosine = if inosine { tgcn('A') } else { 0.0 };
+
+        // Watson-Crick partner first, then the wobble partner
+        let w = match bases[2] {
+            'T' => tgcn('A') + (1.0 - wobble.g_u) * tgcn('G'),
+            'C' => tgcn('G') + (1.0 - wobble.i_c) * tgcn_inosine,
+            'A' => tgcn('T') + (1.0 - wobble.i_a) * tgcn_inosine,
+            _ => tgcn('C') + (1.0 - wobble.u_g) * tgcn('T'),
+        };
+        absolute.insert(codon, w);
+    }
+
+    let max_w = absolute.values().copied().fold(0.0, f64::max);
+    if max_w <= 0.0 {
+        anyhow::bail!("None of the tRNAs decode a sense codon")
+    }
+
+    let mut weights: RelativeAdaptivenessTable = absolute
+        .into_iter()
+        .map(|(codon, w)| (codon, w / max_w))
+        .collect();
+
+    let decoded: Vec<f64> = weights.values().copied().filter(|w| *w > 0.0).collect();
+    let mean_w = (decoded.iter().map(|w| w.ln()).sum::<f64>() / decoded.len() as f64).exp();
+    for w in weights.values_mut() {
+        if *w <= 0.0 {
+            *w = mean_w;
+        }
+    }
+
+    Ok(weights)
+}
+
+///
+/// Compute the tRNA adaptation index (tAI) of a DNA sequence -- the geometric mean of the
+/// tRNA adaptation weights of its codons. Start (ATG) and stop codons are skipped.
+///
+/// # Arguments
+/// - dna sequence
+/// - weights, see `compute_tai_weights`
+///
+/// # Returns
+/// - the tai, between 0 and 1
+///
+pub fn compute_tai(dna: &str, weights: &RelativeAdaptivenessTable) -> Result<f64> {
+    let codons = split_into_codons(&dna.to_uppercase())?;
+
+    let log_weights: Vec<f64> = codons
+        .iter()
+        .filter(|codon| **codon != Codon::ATG)
+        .filter_map(|codon| weights.get(codon))
+        .map(|w| w.ln())
+        .collect();
+
+    if log_weights.is_empty() {
+        return Ok(0.0);
+    }
+
+    Ok((log_weights.iter().sum::<f64>() / log_weights.len() as f64).exp())
+}
+
+///
+/// Turn tRNA adaptation weights into a codon usage table, so that tAI can be used as the
+/// target of any optimizer. The preference of a codon is its weight relative to the
+/// weights of its synonymous codons. Stop codons are used equally.
+///
+/// # Arguments
+/// - weights, see `compute_tai_weights`
+///
+/// # Returns
+/// - the codon usage table, normalized per residue
+///
+pub fn tai_codon_usage(weights: &RelativeAdaptivenessTable) -> CodonUsageByResidue {
+    AACodonLibrary::new()
+        .into_iter()
+        .map(|(aa, codons)| {
+            let codon_weights: Vec<(Codon, f64)> = codons
+                .into_iter()
+                .map(|codon| (codon, weights.get(&codon).copied().unwrap_or(1.0)))
+                .collect();
+            let total: f64 = codon_weights.iter().map(|(_, w)| w).sum();
+            let preferences = codon_weights
+                .into_iter()
+                .map(|(codon, w)| (codon, w / total))
+                .collect();
+            (aa, preferences)
+        })
+        .collect()
+}
+
+///
+/// Optimize a query sequence for the tRNA pool of a host instead of its codon usage
+///
+/// # Arguments
+/// - query seq
+/// - tRNA gene copy numbers of the host by anticodon
+/// - wobble pairing weights, e.g. `WobbleWeights::bacterial` for a bacterial host
+/// - options for the optimization algorithm
+///
+/// # Returns
+/// - optimized sequence
+///
+pub fn optimize_for_tai(
+    query: &str,
+    copy_numbers: &TrnaGeneCopyNumbers,
+    wobble: &WobbleWeights,
+    options: &OptimizationOptions,
+) -> Result<OptimizationResult> {
+    let weights = compute_tai_weights(copy_numbers, wobble)?;
+    optimize_for_single_organism(query, &tai_codon_usage(&weights), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    use crate::strategies::MostFrequentCodonStrategy;
+
+    const EPSILON: f64 = 1e-9;
+
+    #[fixture]
+    fn copy_numbers() -> TrnaGeneCopyNumbers {
+        HashMap::from([
+            // glycine: GCC decodes GGC and (wobble) GGT, TCC decodes GGA and (wobble) GGG
+            ("GCC".to_string(), 4),
+            ("UCC".to_string(), 2),
+            // lysine: TTT decodes AAA and (wobble) AAG
+            ("TTT".to_string(), 2),
+        ])
+    }
+
+    #[rstest]
+    fn test_compute_tai_weights(copy_numbers: TrnaGeneCopyNumbers) {
+        let weights = compute_tai_weights(&copy_numbers, &WobbleWeights::default()).unwrap();
+
+        assert_eq!(weights.len(), 61);
+        assert_eq!((weights[&Codon::GGC] - 1.0).abs() < EPSILON, true);
+        assert_eq!((weights[&Codon::GGT] - 0.59).abs() < EPSILON, true);
+        assert_eq!((weights[&Codon::GGA] - 0.5).abs() < EPSILON, true);
+        assert_eq!((weights[&Codon::GGG] - 0.16).abs() < EPSILON, true);
+        assert_eq!((weights[&Codon::AAG] - 0.16).abs() < EPSILON, true);
+
+        let tai = compute_tai("ATGGGCGGA", &weights).unwrap();
+        assert_eq!((tai - 0.5f64.sqrt()).abs() < EPSILON, true);
+
+        // without inosine, tRNA-Ala(AGC) only decodes GCT, while tRNA-Arg(ACG) is modified
+        let copy_numbers = HashMap::from([("AGC".to_string(), 1), ("ACG".to_string(), 1)]);
+        let eukaryotic = compute_tai_weights(&copy_numbers, &WobbleWeights::default()).unwrap();
+        let bacterial = compute_tai_weights(&copy_numbers, &WobbleWeights::bacterial()).unwrap();
+        assert_eq!((eukaryotic[&Codon::GCC] - 0.72).abs() < EPSILON, true);
+        assert_eq!(bacterial[&Codon::GCC] < 0.72, true);
+        assert_eq!((bacterial[&Codon::CGC] - 0.72).abs() < EPSILON, true);
+
+        assert_eq!(
+            compute_tai_weights(
+                &HashMap::from([("GCX".to_string(), 1)]),
+                &WobbleWeights::default()
+            )
+            .is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_optimize_for_tai(copy_numbers: TrnaGeneCopyNumbers) {
+        let options = OptimizationOptions {
+            strategy: Box::new(MostFrequentCodonStrategy),
+            ..Default::default()
+        };
+        let result =
+            optimize_for_tai("GGKG", &copy_numbers, &WobbleWeights::default(), &options).unwrap();
+        assert_eq!(result.seq, "GGCGGCAAAGGC");
+    }
+}
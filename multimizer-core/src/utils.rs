@@ -3,15 +3,25 @@ use std::io::Read;
 
 use anyhow::Result;
 use bio::io::fasta;
-use rand::distributions::WeightedIndex;
-use rand::prelude::*;
-use rand_chacha::ChaCha8Rng;
 
-use crate::consts::{CodonToAA, SequenceType, VALID_AMINO_ACIDS, VALID_NUCLEOTIDES};
-use crate::models::Codon;
+use crate::consts::{
+    AACodonLibrary, CodonToAA, SequenceType, VALID_AMINO_ACIDS, VALID_IUPAC_NUCLEOTIDES,
+    VALID_NUCLEOTIDES,
+};
+use crate::models::{Codon, CodonUsage};
 use crate::optimizations::{CodonUsageByResidue, CodonUsageByResidueByOrganism, SpeciesWeights};
+use crate::sampling::CodonSampler;
 
 pub type RCAxyzTable = HashMap<Codon, f64>;
+pub type RelativeAdaptivenessTable = HashMap<Codon, f64>;
+
+/// Relative adaptiveness given to codons that are never used, so that a single
+/// unused codon doesn't zero out the whole index
+pub const MIN_RELATIVE_ADAPTIVENESS: f64 = 0.01;
+
+/// Number of codons assumed for the reference set of an RCAxyz table built from
+/// frequencies, which sets the size of the pseudocount (see `compute_rca_xyz_table`)
+pub const DEFAULT_RCA_REFERENCE_SIZE: f64 = 100_000.0;
 
 ///
 /// This function does three things in turn:
@@ -61,10 +71,10 @@ pub fn remove_prohibited_codons(
         for (aa, preferences) in org_usage_data {
             for (codon, pref) in preferences {
                 if *pref < prohibited_threshold {
-                    if prohibited_codons.contains_key(aa) {
-                        prohibited_codons.get_mut(aa).unwrap().push(*codon);
-                    } else {
-                        prohibited_codons.insert(*aa, vec![*codon]);
+                    let prohibited_for_aa = prohibited_codons.entry(*aa).or_default();
+                    // a codon can be prohibited by more than one organism, only count it once
+                    if !prohibited_for_aa.contains(codon) {
+                        prohibited_for_aa.push(*codon);
                     }
                 }
             }
@@ -73,8 +83,7 @@ pub fn remove_prohibited_codons(
 
     // step 2 -- identify inaccessable residues (residues with all codons prohibited)
     for (aa, codons) in prohibited_codons.iter() {
-        if codons.len() == *num_codons_by_residue.get(aa).unwrap() as usize {
-            todo!("What do we do with residues that have no accessible codons?");
+        if codons.len() == num_codons_by_residue.get(aa).copied().unwrap_or(1) as usize {
             return Err(anyhow::anyhow!("Residue {} has no accessible codons", aa));
         }
     }
@@ -113,6 +122,37 @@ pub fn remove_prohibited_codons(
     Ok(renormalized_usage_data)
 }
 
+///
+/// Normalize a codon usage table so that the preferences for each residue sum to one.
+/// Tables built from raw codon counts (see `CodonUsage::into_fracs`) are fractions of
+/// *all* codons, which makes per-residue thresholds meaningless until they are normalized.
+///
+/// # Arguments
+/// - codon_usage: Codon usage data for a single organism
+///
+/// # Returns
+/// - the normalized table
+///
+pub fn normalize_codon_usage(codon_usage: &CodonUsageByResidue) -> CodonUsageByResidue {
+    codon_usage
+        .iter()
+        .map(|(aa, preferences)| {
+            let total: f64 = preferences.values().sum();
+            let normalized = preferences
+                .iter()
+                .map(|(codon, pref)| {
+                    if total > 0.0 {
+                        (*codon, pref / total)
+                    } else {
+                        (*codon, 0.0)
+                    }
+                })
+                .collect();
+            (*aa, normalized)
+        })
+        .collect()
+}
+
 ///
 /// This function builds an averaged codon usage table based on the provided usage data and species weights.
 ///
@@ -220,6 +260,9 @@ pub fn detect_sequence_type(query: &str) -> Result<SequenceType> {
 ///
 /// Converts a DNA sequence to a protein sequence
 ///
+/// Stop codons are translated to `*`, the same symbol `AACodonLibrary` uses, so the
+/// translation can be passed back to the optimizers.
+///
 /// # Arguments
 /// - query
 ///
@@ -231,7 +274,7 @@ pub fn translate_dna_sequence(query: &str) -> Result<String> {
     let mut translated_sequence = String::new();
 
     // verify sequence length
-    if query.len() % 3 != 0 {
+    if !query.len().is_multiple_of(3) {
         anyhow::bail!("The sequence cannot be translated because it is not divisible by 3!")
     }
 
@@ -264,120 +307,540 @@ pub fn translate_dna_sequence(query: &str) -> Result<String> {
 /// # Returns
 /// - selected codon
 ///
+#[deprecated(note = "reseeds on every call; draw codons from a `CodonSampler` instead")]
 pub fn select_random_codon_from_usage_table(
     residue: char,
     usage_data: &CodonUsageByResidue,
     seed: Option<i32>,
 ) -> Result<Codon> {
     let seed = seed.unwrap_or(42);
-    let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
-    if let Some(usage_for_residue) = usage_data.get(&residue) {
-        // quickly iterate to get paired list of codons and weights
-        let (codons, weightings): (Vec<Codon>, Vec<f64>) = usage_for_residue.iter().unzip();
-        let dist = WeightedIndex::new(weightings)?;
-        Ok(codons[dist.sample(&mut rng)])
-    } else {
-        anyhow::bail!("Invalid residue passed in: {residue}")
+    match usage_data.get(&residue) {
+        Some(usage_for_residue) => {
+            let usage = HashMap::from([(residue, usage_for_residue.clone())]);
+            CodonSampler::new(&usage, seed as u64)?.sample(residue)
+        }
+        None => anyhow::bail!("Invalid residue passed in: {residue}"),
     }
 }
 
 ///
-/// Compute the RCAxyz table for a given codon usage data table
-///
-/// It works according to the following formula:
-/// $$
-/// rca_{xyz}(codon) = \frac{f(codon)}{f1(x) * f2(y) * f3(z)}
-/// $$
+/// Split a DNA sequence into its codons
 ///
 /// # Arguments
-/// - codon counds for an organism
+/// - dna sequence
 ///
 /// # Returns
-/// - the computed rca table
+/// - the codons, in order
 ///
-pub fn compute_rca_xyz_table(codon_usage: &CodonUsageByResidue) -> RCAxyzTable {
-    // 1) Compute base frequency by position
-    //    base_position[pos][base], with pos in {0,1,2} for codon positions
-    let mut base_position: [HashMap<char, f64>; 3] =
-        [HashMap::new(), HashMap::new(), HashMap::new()];
-    // We'll need these sums to normalize the frequencies
-    let mut base_position_sums = [0.0, 0.0, 0.0];
-
-    for codons in codon_usage.values() {
-        for (codon, &count) in codons {
-            // codon is something like "ATG"
-            let count_f64 = count;
-            for (i, base_char) in codon.to_string().chars().enumerate() {
-                *base_position[i].entry(base_char).or_insert(0.0) += count_f64;
-            }
-        }
+pub fn split_into_codons(dna: &str) -> Result<Vec<Codon>> {
+    if !dna.len().is_multiple_of(3) {
+        anyhow::bail!("DNA length is not multiple of 3!")
     }
-    // Convert each position’s counts into frequencies
-    // First find sum per position
-    for (pos, base_map) in base_position.iter().enumerate() {
-        let sum_pos: f64 = base_map.values().sum();
-        base_position_sums[pos] = sum_pos;
+
+    dna.as_bytes()
+        .chunks(3)
+        .map(|chunk| {
+            let codon = std::str::from_utf8(chunk)?;
+            Codon::try_from(codon)
+                .map_err(|e| anyhow::anyhow!("There was an error reading the sequence codon: {e}"))
+        })
+        .collect()
+}
+
+///
+/// Check if a nucleotide matches an IUPAC nucleotide code (e.g. `R` matches `A` and `G`)
+///
+/// # Arguments
+/// - code: the IUPAC code
+/// - base: the nucleotide to check
+///
+/// # Returns
+/// - whether the nucleotide matches
+///
+pub fn iupac_matches(code: u8, base: u8) -> bool {
+    let base = base.to_ascii_uppercase();
+    let allowed: &[u8] = match code.to_ascii_uppercase() {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => b"",
+    };
+    allowed.contains(&base)
+}
+
+///
+/// Find every (possibly overlapping) match of an IUPAC pattern in a DNA sequence
+///
+/// # Arguments
+/// - dna sequence
+/// - pattern, made up of IUPAC nucleotide codes
+///
+/// # Returns
+/// - the start position of every match
+///
+pub fn find_iupac_matches(dna: &str, pattern: &str) -> Vec<usize> {
+    let pattern = pattern.as_bytes();
+    if pattern.is_empty() || pattern.len() > dna.len() {
+        return vec![];
     }
-    // Normalize: base_position[pos][base] /= sum for that pos
-    for pos in 0..3 {
-        for base_val in base_position[pos].values_mut() {
-            *base_val /= base_position_sums[pos];
-        }
+
+    dna.as_bytes()
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            window
+                .iter()
+                .zip(pattern)
+                .all(|(base, code)| iupac_matches(*code, *base))
+        })
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+///
+/// Complement a nucleotide given as an ASCII byte. IUPAC codes are complemented too.
+///
+pub fn complement(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        other => other, // S, W and N are their own complement
     }
+}
 
-    // 2) Finally compute rca_xyz(codon) = f(xyz) / (f1(x)*f2(y)*f3(z))
-    let mut rca_xyz: HashMap<Codon, f64> = HashMap::new();
-    for codons in codon_usage.values() {
-        for (cdon, &freq) in codons {
-            let mut pos_factor = 1.0;
-            for (i, base_char) in cdon.to_string().chars().enumerate() {
-                if let Some(bfreq) = base_position[i].get(&base_char) {
-                    pos_factor *= bfreq;
-                } else {
-                    pos_factor = 0.0;
-                    break;
-                }
-            }
-            if pos_factor > 0.0 {
-                rca_xyz.insert(*cdon, freq / pos_factor);
+///
+/// Compute the reverse complement of a DNA sequence. IUPAC codes are complemented too.
+///
+/// # Arguments
+/// - dna sequence
+///
+/// # Returns
+/// - the reverse complement
+///
+pub fn reverse_complement(dna: &str) -> String {
+    dna.chars()
+        .rev()
+        .map(|c| {
+            if c.is_ascii() {
+                complement(c as u8) as char
             } else {
-                rca_xyz.insert(*cdon, 0.0);
+                c
             }
-        }
+        })
+        .collect()
+}
+
+///
+/// Compute the reverse complement of a DNA sequence given as ASCII bytes
+///
+/// # Arguments
+/// - dna sequence
+///
+/// # Returns
+/// - the reverse complement
+///
+pub fn reverse_complement_bytes(bases: &[u8]) -> Vec<u8> {
+    bases.iter().rev().map(|base| complement(*base)).collect()
+}
+
+///
+/// Validate an IUPAC nucleotide pattern, returning it in upper case
+///
+pub fn validate_iupac_pattern(pattern: &str) -> Result<String> {
+    let pattern = pattern.to_uppercase();
+    if pattern.is_empty() {
+        anyhow::bail!("Sequence patterns can't be empty")
+    }
+    if let Some(invalid) = pattern
+        .chars()
+        .find(|c| !VALID_IUPAC_NUCLEOTIDES.contains(*c))
+    {
+        anyhow::bail!("Invalid nucleotide code {invalid} in pattern {pattern}")
     }
+    Ok(pattern)
+}
 
-    rca_xyz
+///
+/// Compute the GC content of a DNA sequence, as a fraction between 0 and 1
+///
+/// # Arguments
+/// - dna sequence
+///
+/// # Returns
+/// - the gc content (0.0 for an empty sequence)
+///
+pub fn compute_gc_content(dna: &str) -> f64 {
+    if dna.is_empty() {
+        return 0.0;
+    }
+
+    let gc = dna
+        .bytes()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+        .count();
+
+    gc as f64 / dna.len() as f64
 }
 
-/// TODO: this was from chat... lets verify that this works.
-/// Compute the overall RCA for a given DNA string, using the rca_xyz_table.
-/// We'll assume optimized_dna.len() is a multiple of 3, and every codon is present in the table.
-pub fn compute_rca(optimized_dna: &str, rca_xyz_table: &RCAxyzTable) -> Result<f64> {
-    let length = optimized_dna.len();
-    if length % 3 != 0 {
+///
+/// Compute how far the realized codon usage of a DNA sequence deviates from a target usage table.
+///
+/// For every residue in the sequence that has more than one codon to choose from, the fraction
+/// of each codon among that residue's occurrences is compared to the (per-residue normalized)
+/// target preference. The error is the root mean square of those differences, so `0.0` means
+/// the sequence reproduces the target distribution exactly.
+///
+/// # Arguments
+/// - dna sequence to score
+/// - target codon usage data
+///
+/// # Returns
+/// - the error
+///
+pub fn compute_codon_usage_error(dna: &str, target: &CodonUsageByResidue) -> Result<f64> {
+    if !dna.len().is_multiple_of(3) {
         anyhow::bail!("DNA length is not multiple of 3!")
     }
 
-    let num_codons = length / 3;
-    let mut product = 1.0f64;
+    let codon_to_aa_map = CodonToAA::new();
+    let target = normalize_codon_usage(target);
+    let mut realized_counts: HashMap<char, HashMap<Codon, f64>> = HashMap::new();
 
-    for chunk in optimized_dna.as_bytes().chunks(3) {
-        let codon = std::str::from_utf8(chunk).unwrap(); // e.g. "ATG"
+    for chunk in dna.as_bytes().chunks(3) {
+        let codon = std::str::from_utf8(chunk)?;
         let codon = match Codon::try_from(codon) {
             Ok(codon) => codon,
             Err(e) => anyhow::bail!("There was an error reading the sequence codon: {e}"),
         };
+        let aa = codon_to_aa_map.convert(&codon).unwrap();
+        *realized_counts
+            .entry(aa)
+            .or_default()
+            .entry(codon)
+            .or_insert(0.0) += 1.0;
+    }
+
+    let mut squared_error = 0.0;
+    let mut num_terms = 0;
+
+    for (aa, counts) in &realized_counts {
+        let Some(preferences) = target.get(aa) else {
+            continue;
+        };
+        if preferences.len() < 2 {
+            continue;
+        }
+
+        let total: f64 = counts.values().sum();
+        for (codon, pref) in preferences {
+            let realized = counts.get(codon).copied().unwrap_or(0.0) / total;
+            squared_error += (realized - pref).powi(2);
+            num_terms += 1;
+        }
+    }
+
+    if num_terms == 0 {
+        return Ok(0.0);
+    }
+
+    Ok((squared_error / num_terms as f64).sqrt())
+}
+
+///
+/// Compute the relative adaptiveness (w) of every codon -- its preference divided by
+/// the preference of the most used codon for the same residue.
+///
+/// Residues that only have a single codon (and stop codons) carry no information about
+/// codon choice and are left out of the table.
+///
+/// # Arguments
+/// - codon usage data for an organism
+///
+/// # Returns
+/// - the relative adaptiveness table
+///
+pub fn compute_relative_adaptiveness(
+    codon_usage: &CodonUsageByResidue,
+) -> RelativeAdaptivenessTable {
+    let mut weights: RelativeAdaptivenessTable = HashMap::new();
+
+    for (aa, preferences) in codon_usage {
+        if *aa == '*' || preferences.len() < 2 {
+            continue;
+        }
+
+        let max_pref = preferences.values().copied().fold(0.0, f64::max);
+        if max_pref <= 0.0 {
+            continue;
+        }
+
+        for (codon, pref) in preferences {
+            weights.insert(*codon, (pref / max_pref).max(MIN_RELATIVE_ADAPTIVENESS));
+        }
+    }
+
+    weights
+}
+
+/// Group codon counts by the residue they encode
+fn group_counts_by_residue(counts: &HashMap<Codon, f64>) -> CodonUsageByResidue {
+    AACodonLibrary::new()
+        .into_iter()
+        .map(|(aa, codons)| {
+            let counts = codons
+                .into_iter()
+                .map(|codon| (codon, counts.get(&codon).copied().unwrap_or(0.0)))
+                .collect();
+            (aa, counts)
+        })
+        .collect()
+}
+
+///
+/// Compute the relative adaptiveness (w) of every codon from a codon count table, e.g. the
+/// usage of a whole genome
+///
+/// # Arguments
+/// - codon counts for an organism
+///
+/// # Returns
+/// - the relative adaptiveness table
+///
+pub fn compute_relative_adaptiveness_from_codon_usage(
+    codon_usage: &CodonUsage,
+) -> RelativeAdaptivenessTable {
+    let counts: HashMap<Codon, f64> = codon_usage
+        .codon_usage
+        .iter()
+        .map(|(codon, count)| (*codon, *count as f64))
+        .collect();
+    compute_relative_adaptiveness(&group_counts_by_residue(&counts))
+}
+
+///
+/// Compute the relative adaptiveness (w) of every codon from a reference set of highly
+/// expressed genes (e.g. ribosomal proteins), as in the original CAI by Sharp and Li (1987).
+/// Codons that never occur in the reference set get `MIN_RELATIVE_ADAPTIVENESS`.
+///
+/// # Arguments
+/// - coding sequences of the reference genes, in frame
+///
+/// # Returns
+/// - the relative adaptiveness table
+///
+pub fn compute_relative_adaptiveness_from_sequences<S: AsRef<str>>(
+    reference_sequences: &[S],
+) -> Result<RelativeAdaptivenessTable> {
+    let mut counts: HashMap<Codon, f64> = HashMap::new();
+    for sequence in reference_sequences {
+        for codon in split_into_codons(&sequence.as_ref().to_uppercase())? {
+            *counts.entry(codon).or_insert(0.0) += 1.0;
+        }
+    }
+
+    if counts.is_empty() {
+        anyhow::bail!("The reference set doesn't contain any codons")
+    }
+
+    Ok(compute_relative_adaptiveness(&group_counts_by_residue(
+        &counts,
+    )))
+}
+
+///
+/// Compute the codon adaptation index (CAI) of a DNA sequence -- the geometric mean of
+/// the relative adaptiveness of its codons. Codons of residues the table doesn't cover at all
+/// (single codon residues and stop codons) are skipped. A codon missing from a residue the
+/// table does cover was never seen in the reference, so it gets `MIN_RELATIVE_ADAPTIVENESS`
+/// like any other unused codon.
+///
+/// # Arguments
+/// - dna sequence
+/// - relative adaptiveness table, see `compute_relative_adaptiveness`
+///
+/// # Returns
+/// - the cai, between 0 and 1
+///
+pub fn compute_cai(dna: &str, weights: &RelativeAdaptivenessTable) -> Result<f64> {
+    let codons = split_into_codons(dna)?;
+
+    let codon_to_aa = CodonToAA::new();
+    let covered: Vec<char> = weights
+        .keys()
+        .filter_map(|codon| codon_to_aa.convert(codon))
+        .collect();
+
+    let log_weights: Vec<f64> = codons
+        .iter()
+        .filter_map(|codon| match weights.get(codon) {
+            Some(w) => Some(*w),
+            None => codon_to_aa
+                .convert(codon)
+                .filter(|aa| covered.contains(aa))
+                .map(|_| MIN_RELATIVE_ADAPTIVENESS),
+        })
+        .map(|w| w.ln())
+        .collect();
+
+    if log_weights.is_empty() {
+        return Ok(0.0);
+    }
+
+    Ok((log_weights.iter().sum::<f64>() / log_weights.len() as f64).exp())
+}
+
+///
+/// Compute the RCAxyz value of every sense codon from codon counts (Fox & Erill 2010)
+///
+/// It works according to the following formula:
+/// $$
+/// rca_{xyz}(codon) = \frac{f(xyz)}{f_1(x) f_2(y) f_3(z)}
+/// $$
+/// where f(xyz) is the frequency of the codon and f_i the frequency of a base at codon
+/// position i, both over the sense codons of the reference set. Stop codons are left out
+/// of the table. Like in the paper, a pseudocount of 1/N (N being the number of codons of
+/// the reference set) is added to both terms, so codons that never occur get a small
+/// value instead of zero and rarely seen ones aren't overrated:
+/// $$
+/// rca_{xyz}(codon) = \frac{f(xyz) + 1/N}{f_1(x) f_2(y) f_3(z) + 1/N}
+/// $$
+///
+/// # Arguments
+/// - codon counts of the reference set, e.g. the genome of an organism
+///
+/// # Returns
+/// - the computed rca table
+///
+pub fn compute_rca_xyz_table(codon_usage: &CodonUsage) -> RCAxyzTable {
+    let counts: HashMap<Codon, f64> = codon_usage
+        .codon_usage
+        .iter()
+        .map(|(codon, count)| (*codon, (*count).max(0) as f64))
+        .collect();
+    rca_xyz_table_from_counts(&counts, None)
+}
 
-        if let Some(&rca_val) = rca_xyz_table.get(&codon) {
-            product *= rca_val;
+///
+/// Compute the RCAxyz table from a codon usage table, see `compute_rca_xyz_table`.
+///
+/// The preferences have to be proportional to the codon counts, as they are for tables
+/// converted from a `CodonUsage`. Tables normalized per residue (see
+/// `normalize_codon_usage`) lose the amino acid composition and give distorted values.
+/// As the size of the reference set is lost too, the pseudocount is taken for
+/// `DEFAULT_RCA_REFERENCE_SIZE` codons -- prefer `compute_rca_xyz_table` when the
+/// counts are at hand.
+///
+/// # Arguments
+/// - codon usage data for an organism
+///
+/// # Returns
+/// - the computed rca table
+///
+pub fn compute_rca_xyz_table_from_usage(codon_usage: &CodonUsageByResidue) -> RCAxyzTable {
+    let counts: HashMap<Codon, f64> = codon_usage
+        .values()
+        .flat_map(|preferences| preferences.iter().map(|(codon, pref)| (*codon, *pref)))
+        .collect();
+    rca_xyz_table_from_counts(&counts, Some(DEFAULT_RCA_REFERENCE_SIZE))
+}
+
+/// Build the RCAxyz table, taking the pseudocount for `reference_size` codons, or for the
+/// number of sense codons counted if not given
+fn rca_xyz_table_from_counts(
+    counts: &HashMap<Codon, f64>,
+    reference_size: Option<f64>,
+) -> RCAxyzTable {
+    let codon_to_aa = CodonToAA::new();
+    let sense_codons: Vec<Codon> = Codon::ALL
+        .into_iter()
+        .filter(|codon| codon_to_aa.convert(codon) != Some('*'))
+        .collect();
+
+    // codon and positional base frequencies over the sense codons
+    let count = |codon: &Codon| counts.get(codon).copied().unwrap_or(0.0).max(0.0);
+    let total: f64 = sense_codons.iter().map(count).sum();
+    let mut base_frequencies: [HashMap<char, f64>; 3] =
+        [HashMap::new(), HashMap::new(), HashMap::new()];
+    for codon in &sense_codons {
+        for (position, base) in codon.to_string().chars().enumerate() {
+            *base_frequencies[position].entry(base).or_insert(0.0) += count(codon);
+        }
+    }
+
+    let pseudocount = 1.0 / reference_size.unwrap_or(total).max(1.0);
+    let mut rca_xyz: RCAxyzTable = HashMap::new();
+    for codon in &sense_codons {
+        let (frequency, expected) = if total > 0.0 {
+            let expected: f64 = codon
+                .to_string()
+                .chars()
+                .enumerate()
+                .map(|(position, base)| base_frequencies[position][&base] / total)
+                .product();
+            (count(codon) / total, expected)
         } else {
-            // skip!
+            (0.0, 0.0)
+        };
+        rca_xyz.insert(*codon, (frequency + pseudocount) / (expected + pseudocount));
+    }
+
+    rca_xyz
+}
+
+///
+/// Compute the relative codon adaptation (RCA) of a DNA sequence -- the geometric mean of
+/// the RCAxyz values of its codons (Fox & Erill 2010). Stop codons are skipped.
+///
+/// # Arguments
+/// - dna sequence
+/// - rca table, see `compute_rca_xyz_table`
+///
+/// # Returns
+/// - the rca, or an error if a sense codon is missing from the table
+///
+pub fn compute_rca(dna: &str, rca_xyz_table: &RCAxyzTable) -> Result<f64> {
+    let codon_to_aa = CodonToAA::new();
+    let codons = split_into_codons(&dna.to_uppercase())?;
+
+    let mut log_values: Vec<f64> = Vec::with_capacity(codons.len());
+    for codon in codons {
+        if codon_to_aa.convert(&codon) == Some('*') {
+            continue;
+        }
+        match rca_xyz_table.get(&codon) {
+            Some(value) => log_values.push(value.ln()),
+            None => anyhow::bail!("Codon {codon} is missing from the RCA table"),
         }
     }
 
-    let rca = product.powf(1.0 / (num_codons as f64));
+    if log_values.is_empty() {
+        return Ok(0.0);
+    }
 
-    Ok(rca)
+    // summing logs rather than multiplying keeps long sequences from underflowing
+    Ok((log_values.iter().sum::<f64>() / log_values.len() as f64).exp())
 }
 
 #[cfg(test)]
@@ -467,6 +930,8 @@ mod tests {
     fn test_translate_dna_sequence() {
         // successful case
         assert_eq!(translate_dna_sequence("ATGGCC").unwrap(), "MA");
+        assert_eq!(translate_dna_sequence("ATGGCCTAA").unwrap(), "MA*");
+        assert_eq!(translate_dna_sequence("TAATAGTGA").unwrap(), "***");
 
         // unsuccessful cases
         assert_eq!(translate_dna_sequence("ATGGC").is_err(), true); // Not divisible by 3
@@ -576,4 +1041,202 @@ mod tests {
             }
         }
     }
+
+    #[rstest]
+    fn test_compute_codon_usage_error(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        // 1x GCT, 2x GCC, 3x GCA, 4x GCG reproduces the alanine preferences exactly
+        let exact = "GCTGCCGCCGCAGCAGCAGCGGCGGCGGCG";
+        let error = compute_codon_usage_error(exact, &org_usage1).unwrap();
+        assert_eq!(approx_equal(error, 0.0, EPSILON), true);
+
+        // only ever using GCG is off by 0.1, 0.2, 0.3 and 0.6
+        let skewed = "GCGGCGGCGGCG";
+        let error = compute_codon_usage_error(skewed, &org_usage1).unwrap();
+        let expected = ((0.01 + 0.04 + 0.09 + 0.36) / 4.0_f64).sqrt();
+        assert_eq!(approx_equal(error, expected, EPSILON), true);
+
+        assert_eq!(
+            compute_codon_usage_error("GCGG", &org_usage1).is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_compute_cai(org_usage1: HashMap<char, HashMap<Codon, f64>>) {
+        let weights = compute_relative_adaptiveness(&org_usage1);
+        assert_eq!(
+            approx_equal(*weights.get(&Codon::GCG).unwrap(), 1.0, EPSILON),
+            true
+        );
+        assert_eq!(
+            approx_equal(*weights.get(&Codon::GCT).unwrap(), 0.25, EPSILON),
+            true
+        );
+
+        // only the most used codons
+        let cai = compute_cai("GCGCGG", &weights).unwrap();
+        assert_eq!(approx_equal(cai, 1.0, EPSILON), true);
+
+        // geometric mean of 0.25 and 1.0
+        let cai = compute_cai("GCTCGG", &weights).unwrap();
+        assert_eq!(approx_equal(cai, 0.5, EPSILON), true);
+
+        // a codon missing for a residue the table covers counts as unused, while methionine
+        // isn't in the table at all and is skipped
+        let mut weights = weights;
+        weights.remove(&Codon::GCT);
+        let cai = compute_cai("GCTGCGATG", &weights).unwrap();
+        assert_eq!(
+            approx_equal(cai, MIN_RELATIVE_ADAPTIVENESS.sqrt(), EPSILON),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_compute_rca() {
+        // ATG twice and GCT once; the stop codons don't count towards the frequencies
+        let mut codon_usage: HashMap<Codon, i32> =
+            Codon::ALL.iter().map(|codon| (*codon, 0)).collect();
+        codon_usage.extend([(Codon::ATG, 2), (Codon::GCT, 1), (Codon::TAA, 5)]);
+        let usage = CodonUsage { codon_usage };
+        let table = compute_rca_xyz_table(&usage);
+        assert_eq!(table.len(), 61);
+        assert_eq!(table.get(&Codon::TAA), None);
+
+        // N = 3, so the pseudocount is 1/3
+        // f1(A) = 2/3, f2(T) = 2/3, f3(G) = 2/3 -- (2/3 + 1/3) / ((2/3)^3 + 1/3)
+        let atg = 1.0 / (8.0 / 27.0 + 1.0 / 3.0);
+        assert_eq!(approx_equal(table[&Codon::ATG], atg, EPSILON), true);
+        // f1(G) = 1/3, f2(C) = 1/3, f3(T) = 1/3 -- (1/3 + 1/3) / ((1/3)^3 + 1/3)
+        let gct = (2.0 / 3.0) / (1.0 / 27.0 + 1.0 / 3.0);
+        assert_eq!(approx_equal(table[&Codon::GCT], gct, EPSILON), true);
+        // unused, f3(G) = 2/3 -- (0 + 1/3) / (2/27 + 1/3)
+        let gcg = (1.0 / 3.0) / (2.0 / 27.0 + 1.0 / 3.0);
+        assert_eq!(approx_equal(table[&Codon::GCG], gcg, EPSILON), true);
+
+        // geometric mean of the values of ATG and GCT, the stop codon is skipped
+        let rca = compute_rca("ATGGCTTAA", &table).unwrap();
+        assert_eq!(approx_equal(rca, (atg * gct).sqrt(), EPSILON), true);
+
+        // the same usage as fractions gives the same frequencies, but the pseudocount
+        // is taken for the default reference size
+        let fractions: CodonUsageByResidue = usage.into();
+        let from_fractions = compute_rca_xyz_table_from_usage(&fractions);
+        let pseudocount = 1.0 / DEFAULT_RCA_REFERENCE_SIZE;
+        assert_eq!(
+            approx_equal(
+                from_fractions[&Codon::GCT],
+                (1.0 / 3.0 + pseudocount) / (1.0 / 27.0 + pseudocount),
+                EPSILON
+            ),
+            true
+        );
+
+        // long sequences don't underflow
+        let rca = compute_rca(&"GCG".repeat(1_000), &table).unwrap();
+        assert_eq!(approx_equal(rca, gcg, EPSILON), true);
+
+        assert_eq!(compute_rca("ATGGC", &table).is_err(), true);
+        assert_eq!(compute_rca("ATG", &HashMap::new()).is_err(), true);
+    }
+
+    // Reference values for RCA, following Fox & Erill, "Relative codon adaptation: a generic
+    // codon bias index for prediction of gene expression", DNA Research 17(3):185-196 (2010).
+    #[rstest]
+    fn test_compute_rca_reference_values() {
+        // RCAxyz is 1 when a codon is exactly as frequent as its positional nucleotide
+        // frequencies predict, so a reference set without any other bias scores every
+        // sequence at 1. Nothing starts with T, which keeps the stop codons out.
+        let first = [(b'A', 1), (b'C', 2), (b'G', 3), (b'T', 0)];
+        let second = [(b'A', 2), (b'C', 1), (b'G', 1), (b'T', 3)];
+        let third = [(b'A', 1), (b'C', 1), (b'G', 2), (b'T', 4)];
+        let codon_usage: HashMap<Codon, i32> = Codon::ALL
+            .iter()
+            .map(|codon| {
+                let bases = codon.to_string().into_bytes();
+                let count = |counts: &[(u8, i32)], base: u8| {
+                    counts.iter().find(|(b, _)| *b == base).unwrap().1
+                };
+                let n =
+                    count(&first, bases[0]) * count(&second, bases[1]) * count(&third, bases[2]);
+                (*codon, n)
+            })
+            .collect();
+        let table = compute_rca_xyz_table(&CodonUsage { codon_usage });
+        for value in table.values() {
+            assert_eq!(approx_equal(*value, 1.0, EPSILON), true);
+        }
+        let rca = compute_rca("ATGGCTGCGCGTAAGTGGTAA", &table).unwrap();
+        assert_eq!(approx_equal(rca, 1.0, EPSILON), true);
+
+        // a biased reference set of 124 sense codons; the expected values were computed
+        // independently from equations 1-3 of the paper in exact rational arithmetic
+        let mut codon_usage: HashMap<Codon, i32> =
+            Codon::ALL.iter().map(|codon| (*codon, 0)).collect();
+        codon_usage.extend([
+            (Codon::ATG, 12),
+            (Codon::GCT, 30),
+            (Codon::GCC, 9),
+            (Codon::GCA, 4),
+            (Codon::GCG, 1),
+            (Codon::CGT, 20),
+            (Codon::CGC, 15),
+            (Codon::AAA, 25),
+            (Codon::AAG, 5),
+            (Codon::TGG, 3),
+            (Codon::TAA, 2),
+        ]);
+        let table = compute_rca_xyz_table(&CodonUsage { codon_usage });
+        let expected = [
+            (Codon::ATG, 24986.0 / 3245.0),
+            (Codon::GCT, 29791.0 / 7011.0),
+            (Codon::GCG, 961.0 / 1751.0),
+            (Codon::AAG, 23064.0 / 10459.0),
+            (Codon::TGG, 30752.0 / 8885.0),
+            (Codon::CGT, 26908.0 / 6823.0),
+        ];
+        for (codon, value) in expected {
+            assert_eq!(approx_equal(table[&codon], value, EPSILON), true);
+        }
+        let rca = compute_rca("ATGGCTGCGCGTAAGTGGTAA", &table).unwrap();
+        assert_eq!(approx_equal(rca, 2.8540734117861244, EPSILON), true);
+    }
+
+    #[rstest]
+    fn test_relative_adaptiveness_from_reference_genes() {
+        // GCT three times, GCC once, AAA and AAG once each
+        let weights =
+            compute_relative_adaptiveness_from_sequences(&["GCTGCTAAA", "gccgctaag"]).unwrap();
+        assert_eq!(
+            approx_equal(*weights.get(&Codon::GCC).unwrap(), 1.0 / 3.0, EPSILON),
+            true
+        );
+        assert_eq!(
+            *weights.get(&Codon::GCG).unwrap(),
+            MIN_RELATIVE_ADAPTIVENESS
+        );
+        assert_eq!(weights.get(&Codon::ATG), None);
+
+        let cai = compute_cai("GCCAAA", &weights).unwrap();
+        assert_eq!(approx_equal(cai, (1.0f64 / 3.0).sqrt(), EPSILON), true);
+
+        assert_eq!(
+            compute_relative_adaptiveness_from_sequences::<&str>(&[]).is_err(),
+            true
+        );
+    }
+
+    #[rstest]
+    fn test_iupac_matching() {
+        assert_eq!(find_iupac_matches("AAGAATTCAA", "GAATTC"), vec![2]);
+        assert_eq!(find_iupac_matches("CTCGAGCCCGGG", "CYCGRG"), vec![0, 6]);
+        assert_eq!(find_iupac_matches("AAAA", "AA"), vec![0, 1, 2]);
+
+        assert_eq!(reverse_complement("GGTCTC"), "GAGACC");
+        assert_eq!(reverse_complement("CYCGRG"), "CYCGRG");
+        assert_eq!(reverse_complement_bytes(b"GGTCTC"), b"GAGACC".to_vec());
+
+        assert_eq!(validate_iupac_pattern("ggtnnc").unwrap(), "GGTNNC");
+        assert_eq!(validate_iupac_pattern("GGXC").is_err(), true);
+    }
 }
@@ -1,15 +1,118 @@
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
+use multimizer::models::{Codon, CodonUsage};
 use multimizer::optimizations::optimize_seq_test;
+use multimizer::profile::{compute_adaptation_profile, ProfileOptions};
 
 #[pyfunction(name = "optimize")]
 fn optimize_py(query: &str) -> PyResult<String> {
     Ok(optimize_seq_test(query))
 }
 
+/// Turn codon counts keyed by codon into a usage table, which needs a count for all 64
+/// codons and a positive total
+fn parse_codon_counts(codon_usage: HashMap<String, i32>) -> Result<CodonUsage, String> {
+    let mut counts = HashMap::new();
+    for (codon, count) in codon_usage {
+        let codon = Codon::try_from(codon.as_str())?;
+        if count < 0 {
+            return Err(format!("Negative count {count} given for codon {codon}"));
+        }
+        counts.insert(codon, count);
+    }
+
+    let missing: Vec<String> = Codon::ALL
+        .iter()
+        .filter(|codon| !counts.contains_key(codon))
+        .map(|codon| codon.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("No count given for codons {}", missing.join(", ")));
+    }
+    if counts.values().all(|count| *count == 0) {
+        return Err("Codon counts add up to zero".to_string());
+    }
+
+    Ok(CodonUsage {
+        codon_usage: counts,
+    })
+}
+
+/// Scores of a single window of an adaptation profile
+#[pyclass(name = "ProfileWindow", get_all)]
+struct PyProfileWindow {
+    start: usize,
+    end: usize,
+    cai: f64,
+    rca: f64,
+    min_max: f64,
+    gc: f64,
+}
+
+/// CAI, RCA, %MinMax and GC content in sliding windows along a DNA sequence, scored
+/// against codon counts keyed by codon (e.g. `{"GCT": 1024, ...}`, all 64 codons)
+#[pyfunction(name = "adaptation_profile")]
+#[pyo3(signature = (sequence, codon_usage, window_size = 18, step = 1))]
+fn adaptation_profile_py(
+    sequence: &str,
+    codon_usage: HashMap<String, i32>,
+    window_size: usize,
+    step: usize,
+) -> PyResult<Vec<PyProfileWindow>> {
+    let codon_usage = parse_codon_counts(codon_usage).map_err(PyValueError::new_err)?;
+
+    let options = ProfileOptions { window_size, step };
+    let profile = compute_adaptation_profile(sequence, &codon_usage.into(), &options)?;
+    Ok(profile
+        .windows
+        .into_iter()
+        .map(|window| PyProfileWindow {
+            start: window.start,
+            end: window.end,
+            cai: window.cai,
+            rca: window.rca,
+            min_max: window.min_max,
+            gc: window.gc,
+        })
+        .collect())
+}
+
 #[pymodule]
 #[pyo3(name = "multimizer")]
 fn multimizer_py(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(optimize_py, m)?)?;
+    m.add_function(wrap_pyfunction!(adaptation_profile_py, m)?)?;
+    m.add_class::<PyProfileWindow>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codon_counts() {
+        let full: HashMap<String, i32> = Codon::ALL
+            .iter()
+            .map(|codon| (codon.to_string(), 1))
+            .collect();
+        assert!(parse_codon_counts(full.clone()).is_ok());
+
+        let partial = HashMap::from([("GCT".to_string(), 10)]);
+        assert!(parse_codon_counts(partial).is_err());
+
+        let zeros: HashMap<String, i32> = full.keys().map(|codon| (codon.clone(), 0)).collect();
+        assert!(parse_codon_counts(zeros).is_err());
+
+        let mut negative = full.clone();
+        negative.insert("GCT".to_string(), -1);
+        assert!(parse_codon_counts(negative).is_err());
+
+        let mut invalid = full;
+        invalid.insert("GCX".to_string(), 1);
+        assert!(parse_codon_counts(invalid).is_err());
+    }
+}
@@ -4,11 +4,13 @@ mod utils;
 
 use multimizer::models::CodonUsage;
 use multimizer::optimizations::{optimize_for_single_organism, OptimizationOptions};
-use multimizer::utils::parse_fasta_sequences_from_string;
+use multimizer::profile::{compute_adaptation_profile, ProfileOptions};
+use multimizer::utils::{compute_rca_xyz_table, parse_fasta_sequences_from_string};
 
 use wasm_bindgen::prelude::*;
 
 use crate::models::{
+    JsAdaptationProfile,
     JsCodonUsage,
     JsOptimizationResult,
     ParsedFastaSequences
@@ -40,7 +42,10 @@ pub fn optimize(query: &str, codon_usage: JsValue) -> Result<JsOptimizationResul
     let codon_usage: JsCodonUsage = serde_wasm_bindgen::from_value(codon_usage)?;
     let codon_usage_rust: CodonUsage = codon_usage.into();
 
-    let opts = OptimizationOptions::default();
+    let opts = OptimizationOptions {
+        rca_reference: Some(compute_rca_xyz_table(&codon_usage_rust)),
+        ..Default::default()
+    };
     let res = optimize_for_single_organism(query, &codon_usage_rust.into(), &opts);
 
     match res {
@@ -49,7 +54,9 @@ pub fn optimize(query: &str, codon_usage: JsValue) -> Result<JsOptimizationResul
                 seq: res.seq,
                 iterations: res. iterations,
                 translated_seq: res.translated_seq,
-                rca_value: res.rca_value
+                rca_value: res.rca_value,
+                cai_value: res.cai_value,
+                error: res.error,
             })
         },
         Err(err) => {
@@ -57,3 +64,20 @@ pub fn optimize(query: &str, codon_usage: JsValue) -> Result<JsOptimizationResul
         }
     }   
 }
+
+#[wasm_bindgen(js_name = "adaptationProfile")]
+pub fn adaptation_profile(
+    sequence: &str,
+    codon_usage: JsValue,
+    window_size: usize,
+    step: usize,
+) -> Result<JsAdaptationProfile, JsError> {
+    let codon_usage: JsCodonUsage = serde_wasm_bindgen::from_value(codon_usage)?;
+    let codon_usage_rust: CodonUsage = codon_usage.into();
+
+    let opts = ProfileOptions { window_size, step };
+    match compute_adaptation_profile(sequence, &codon_usage_rust.into(), &opts) {
+        Ok(profile) => Ok(profile.into()),
+        Err(err) => Err(JsError::new(&err.to_string())),
+    }
+}
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
 use multimizer::models::{CodonUsage, Codon};
+use multimizer::profile::AdaptationProfile;
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 
+#[allow(dead_code)]
 type CodonUsageByResidue = HashMap<char, HashMap<multimizer::models::Codon, f64>>;
 
 #[derive(Tsify, Serialize, Deserialize)]
@@ -19,6 +21,27 @@ pub struct JsOptimizationResult {
     pub iterations: i32,
     pub translated_seq: String,
     pub rca_value: f64,
+    pub cai_value: f64,
+    pub error: f64,
+}
+
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct JsProfileWindow {
+    pub start: usize,
+    pub end: usize,
+    pub cai: f64,
+    pub rca: f64,
+    pub min_max: f64,
+    pub gc: f64,
+}
+
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct JsAdaptationProfile {
+    pub window_size: usize,
+    pub step: usize,
+    pub windows: Vec<JsProfileWindow>,
 }
 
 #[derive(Tsify, Serialize, Deserialize)]
@@ -163,4 +186,25 @@ impl From<JsCodonUsage> for CodonUsage {
            codon_usage 
         }
     }   
-}
\ No newline at end of file
+}
+
+impl From<AdaptationProfile> for JsAdaptationProfile {
+    fn from(value: AdaptationProfile) -> Self {
+        JsAdaptationProfile {
+            window_size: value.window_size,
+            step: value.step,
+            windows: value
+                .windows
+                .into_iter()
+                .map(|window| JsProfileWindow {
+                    start: window.start,
+                    end: window.end,
+                    cai: window.cai,
+                    rca: window.rca,
+                    min_max: window.min_max,
+                    gc: window.gc,
+                })
+                .collect(),
+        }
+    }
+}